@@ -0,0 +1,217 @@
+//! Derive macros for `bevy_tacview`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::{
+    parenthesized, parse_macro_input, Data, DeriveInput, Error, GenericArgument, Ident, LitInt,
+    LitStr, PathArguments, Type,
+};
+
+/// Derive `TacviewComponent` for a component whose fields map to Tacview properties.
+///
+/// ```ignore
+/// #[derive(Component, TacviewObject)]
+/// struct Aircraft {
+///     #[tacview(CallSign)]
+///     callsign: String,
+///     #[tacview(FuelWeight(0))]
+///     fuel_kg: f64,
+///     #[tacview(IAS, unit = "kt")]
+///     speed: f32,
+///     #[tacview(Throttle)]
+///     throttle: Option<f64>,
+/// }
+/// ```
+///
+/// Fields are converted with `PropertyValue`, `Option` fields are only written when set. A value
+/// the field cannot hold, like an object id which is not an entity, unsets an `Option` field and
+/// is ignored by other fields. Numeric fields in another unit than the ACMI one are converted from
+/// `kt`, `km/h`, `ft`, `km`, `nm`, `lb` or `rad`. Fields without a `tacview` attribute are ignored.
+#[proc_macro_derive(TacviewObject, attributes(tacview))]
+pub fn derive_tacview_object(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// A field mapped to a property.
+struct Mapping {
+    member: TokenStream2,
+    property: Ident,
+    index: Option<LitInt>,
+    /// Factor from the unit of the field to the ACMI unit.
+    factor: Option<f64>,
+    optional: bool,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "TacviewObject can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut mappings = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let member = match &field.ident {
+            Some(ident) => ident.to_token_stream(),
+            None => syn::Index::from(i).to_token_stream(),
+        };
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("tacview")) {
+            let mut property = None;
+            let mut index = None;
+            let mut factor = None;
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("unit") {
+                    let unit: LitStr = meta.value()?.parse()?;
+                    factor = Some(unit_factor(&unit)?);
+                } else if let Some(ident) = meta.path.get_ident() {
+                    if meta.input.peek(syn::token::Paren) {
+                        let content;
+                        parenthesized!(content in meta.input);
+                        index = Some(content.parse::<LitInt>()?);
+                    }
+                    property = Some(ident.clone());
+                } else {
+                    return Err(meta.error("expected a property name"));
+                }
+                Ok(())
+            })?;
+            let property =
+                property.ok_or_else(|| Error::new_spanned(attr, "missing property name"))?;
+            mappings.push(Mapping {
+                member: member.clone(),
+                property,
+                index,
+                factor,
+                optional: is_option(&field.ty),
+            });
+        }
+    }
+
+    let writes = mappings.iter().map(write_property);
+    let reads = mappings.iter().map(read_property);
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::bevy_tacview::components::TacviewComponent for #name #ty_generics
+            #where_clause
+        {
+            fn to_properties(&self) -> ::std::vec::Vec<::bevy_tacview::record::Property> {
+                let mut props = ::std::vec::Vec::new();
+                #(#writes)*
+                props
+            }
+
+            #[allow(unreachable_patterns)]
+            fn apply_property(&mut self, property: &::bevy_tacview::record::Property) -> bool {
+                use ::bevy_tacview::components::PropertyValue;
+                match property {
+                    #(#reads)*
+                    _ => return false,
+                }
+                true
+            }
+        }
+    })
+}
+
+fn write_property(mapping: &Mapping) -> TokenStream2 {
+    let Mapping {
+        member,
+        property,
+        index,
+        factor,
+        optional,
+    } = mapping;
+    let value = match factor {
+        Some(factor) => quote! {
+            ::bevy_tacview::components::PropertyValue::<f64>::to_value(value) * #factor
+        },
+        None => quote! { ::bevy_tacview::components::PropertyValue::to_value(value) },
+    };
+    let index = index.iter();
+    let property = quote! {
+        props.push(::bevy_tacview::record::Property::#property(#(#index,)* #value));
+    };
+    if *optional {
+        quote! {
+            if let ::std::option::Option::Some(value) = &self.#member {
+                #property
+            }
+        }
+    } else {
+        quote! {
+            let value = &self.#member;
+            #property
+        }
+    }
+}
+
+fn read_property(mapping: &Mapping) -> TokenStream2 {
+    let Mapping {
+        member,
+        property,
+        index,
+        factor,
+        optional,
+    } = mapping;
+    let value = match factor {
+        Some(factor) => quote! { PropertyValue::from_value(*value / #factor) },
+        None => quote! { PropertyValue::from_value(::std::clone::Clone::clone(value)) },
+    };
+    let assign = if *optional {
+        quote! { self.#member = #value; }
+    } else {
+        quote! {
+            if let ::std::option::Option::Some(value) = #value {
+                self.#member = value;
+            }
+        }
+    };
+    let index = index.iter();
+    quote! {
+        ::bevy_tacview::record::Property::#property(#(#index,)* value) => {
+            #assign
+        }
+    }
+}
+
+fn unit_factor(unit: &LitStr) -> syn::Result<f64> {
+    Ok(match unit.value().as_str() {
+        "kt" => 1852.0 / 3600.0,
+        "km/h" => 1.0 / 3.6,
+        "ft" => 0.3048,
+        "km" => 1000.0,
+        "nm" => 1852.0,
+        "lb" => 0.453_592_37,
+        "rad" => 180.0 / std::f64::consts::PI,
+        _ => {
+            return Err(Error::new_spanned(
+                unit,
+                "unknown unit, expected one of kt, km/h, ft, km, nm, lb or rad",
+            ))
+        }
+    })
+}
+
+fn is_option(ty: &Type) -> bool {
+    let Type::Path(path) = ty else {
+        return false;
+    };
+    path.path.segments.last().is_some_and(|segment| {
+        segment.ident == "Option"
+            && matches!(
+                &segment.arguments,
+                PathArguments::AngleBracketed(args)
+                    if matches!(args.args.first(), Some(GenericArgument::Type(_)))
+            )
+    })
+}
@@ -0,0 +1,94 @@
+//! Analysis of recordings and live objects, with CSV and JSON export of the results.
+
+use std::io::{self, Write};
+
+pub use shots::{
+    shot_log, Kill, KillCount, KillKind, Participant, Shot, ShotCollector, ShotLog, ShotOutcome,
+};
+pub use stats::{flight_stats, FlightStats, StatsCollector};
+pub use takeoff::TakeoffDetector;
+
+mod shots;
+mod stats;
+mod takeoff;
+
+/// A value of an exported row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value<'a> {
+    /// An object id, exported in hexadecimal as in ACMI.
+    Id(u64),
+    Text(Option<&'a str>),
+    Number(Option<f64>),
+    Count(usize),
+}
+
+/// A row of analysis results which can be exported as CSV or JSON.
+pub trait Export {
+    fn columns() -> &'static [&'static str];
+
+    /// One value per column.
+    fn values(&self) -> Vec<Value<'_>>;
+}
+
+/// Write rows as CSV, with a header line.
+pub fn write_csv<R: Export>(rows: &[R], mut wr: impl Write) -> Result<(), io::Error> {
+    writeln!(wr, "{}", R::columns().join(","))?;
+    for row in rows {
+        for (i, value) in row.values().into_iter().enumerate() {
+            if i > 0 {
+                write!(wr, ",")?;
+            }
+            match value {
+                Value::Id(id) => write!(wr, "{id:x}")?,
+                Value::Text(Some(s)) if s.contains([',', '"', '\n', '\r']) => {
+                    write!(wr, "\"{}\"", s.replace('"', "\"\""))?
+                }
+                Value::Text(Some(s)) => write!(wr, "{s}")?,
+                Value::Number(Some(v)) if v.is_finite() => write!(wr, "{v}")?,
+                Value::Count(v) => write!(wr, "{v}")?,
+                Value::Text(None) | Value::Number(_) => {}
+            }
+        }
+        writeln!(wr)?;
+    }
+    Ok(())
+}
+
+/// Write rows as a JSON array of objects keyed by column.
+pub fn write_json<R: Export>(rows: &[R], mut wr: impl Write) -> Result<(), io::Error> {
+    write!(wr, "[")?;
+    for (i, row) in rows.iter().enumerate() {
+        write!(wr, "{}{{", if i > 0 { ",\n" } else { "\n" })?;
+        for (j, (column, value)) in R::columns().iter().zip(row.values()).enumerate() {
+            if j > 0 {
+                write!(wr, ",")?;
+            }
+            write!(wr, "\"{column}\":")?;
+            match value {
+                Value::Id(id) => write!(wr, "\"{id:x}\"")?,
+                Value::Text(Some(s)) => write_json_string(&mut wr, s)?,
+                Value::Number(Some(v)) if v.is_finite() => write!(wr, "{v}")?,
+                Value::Count(v) => write!(wr, "{v}")?,
+                Value::Text(None) | Value::Number(_) => write!(wr, "null")?,
+            }
+        }
+        write!(wr, "}}")?;
+    }
+    writeln!(wr, "{}]", if rows.is_empty() { "" } else { "\n" })
+}
+
+fn write_json_string(wr: &mut impl Write, s: &str) -> Result<(), io::Error> {
+    write!(wr, "\"")?;
+    for ch in s.chars() {
+        match ch {
+            '"' => write!(wr, "\\\"")?,
+            '\\' => write!(wr, "\\\\")?,
+            '\n' => write!(wr, "\\n")?,
+            '\r' => write!(wr, "\\r")?,
+            '\t' => write!(wr, "\\t")?,
+            ch if ch.is_control() => write!(wr, "\\u{:04x}", ch as u32)?,
+            ch => write!(wr, "{ch}")?,
+        }
+    }
+    write!(wr, "\"")
+}
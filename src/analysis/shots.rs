@@ -0,0 +1,584 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::{Export, Value};
+use crate::{
+    record::{Coords, EventKind, Property, Record, Tag},
+    state::{ObjectState, WorldState},
+};
+
+/// Time between a weapon impact and a damage for the damage to be attributed to it, in seconds.
+const ATTRIBUTION_WINDOW: f64 = 10.0;
+
+/// Distance between the last position of a weapon without target and a damaged object for the
+/// damage to be attributed to it, in meters.
+const HIT_RADIUS: f64 = 200.0;
+
+/// Identity of an object when it took part in a shot or kill.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Participant {
+    pub id: u64,
+    pub name: Option<String>,
+    pub call_sign: Option<String>,
+    pub pilot: Option<String>,
+    pub coalition: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShotOutcome {
+    /// The target was destroyed or disabled.
+    Kill,
+    /// The target was damaged.
+    Hit,
+    Miss,
+    /// The weapon was still flying at the end of the recording.
+    Unknown,
+}
+
+/// A weapon launch, from a new object tagged `Weapon` with a `Parent` or a `Timeout` event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shot {
+    pub launch_time: f64,
+    /// Time the weapon was removed or timed out.
+    pub end_time: Option<f64>,
+    pub shooter: Participant,
+    /// The weapon object, `None` for shots only known from a `Timeout` event.
+    pub weapon: Option<Participant>,
+    /// `Name` of the weapon or `AmmoType` of the `Timeout` event.
+    pub weapon_type: Option<String>,
+    /// `LockedTarget` of the weapon, or of the shooter at launch.
+    pub target: Option<Participant>,
+    /// Last position of the weapon.
+    pub impact: Option<Coords>,
+    pub outcome: ShotOutcome,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KillKind {
+    Destroyed,
+    Disabled,
+}
+
+/// An object destroyed or disabled, with the shot it is attributed to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Kill {
+    pub time: f64,
+    pub kind: KillKind,
+    pub victim: Participant,
+    pub killer: Option<Participant>,
+    pub weapon: Option<Participant>,
+    pub weapon_type: Option<String>,
+}
+
+/// Number of kills of a killer against a victim, e.g. by coalition or by pilot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KillCount {
+    pub killer: String,
+    pub victim: String,
+    pub kills: usize,
+}
+
+/// The shots and kills of a recording.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShotLog {
+    pub shots: Vec<Shot>,
+    pub kills: Vec<Kill>,
+}
+
+impl ShotLog {
+    /// Attributed kills by killer and victim, grouped by `key`.
+    pub fn kill_matrix(&self, key: impl Fn(&Participant) -> String) -> Vec<KillCount> {
+        let mut counts = BTreeMap::<_, usize>::new();
+        for kill in &self.kills {
+            if let Some(killer) = &kill.killer {
+                *counts.entry((key(killer), key(&kill.victim))).or_default() += 1;
+            }
+        }
+        counts
+            .into_iter()
+            .map(|((killer, victim), kills)| KillCount {
+                killer,
+                victim,
+                kills,
+            })
+            .collect()
+    }
+
+    /// Kill matrix by `Coalition`.
+    pub fn kills_by_coalition(&self) -> Vec<KillCount> {
+        self.kill_matrix(|p| p.coalition.clone().unwrap_or_default())
+    }
+
+    /// Kill matrix by `Pilot`, or `CallSign` for objects without pilot.
+    pub fn kills_by_pilot(&self) -> Vec<KillCount> {
+        self.kill_matrix(|p| {
+            p.pilot
+                .clone()
+                .or_else(|| p.call_sign.clone())
+                .unwrap_or_else(|| format!("{:x}", p.id))
+        })
+    }
+}
+
+/// Builds a [`ShotLog`] in a single pass over the records of a recording.
+#[derive(Debug, Default)]
+pub struct ShotCollector {
+    state: WorldState,
+    shots: Vec<Shot>,
+    /// Index of the shot of each weapon in flight.
+    in_flight: HashMap<u64, usize>,
+    /// Weapons already counted, until removed.
+    launched: HashSet<u64>,
+    damages: Vec<Damage>,
+    health: HashMap<u64, f64>,
+    /// Objects already destroyed or disabled, until removed.
+    killed: HashSet<u64>,
+}
+
+#[derive(Debug)]
+struct Damage {
+    time: f64,
+    victim: Participant,
+    position: Coords,
+    /// `None` if the victim was only damaged.
+    kill: Option<KillKind>,
+}
+
+impl ShotCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, record: &Record) {
+        let time = self.state.time;
+        match record {
+            Record::Update(update) => {
+                self.state.apply(record);
+                let Some(object) = self.state.get(update.id) else {
+                    return;
+                };
+
+                if !self.launched.contains(&update.id) && object.has_tag(&Tag::Weapon) {
+                    if let Some(shooter) = object.parent().and_then(|id| self.state.get(id)) {
+                        let target = object
+                            .locked_target()
+                            .or_else(|| shooter.locked_target())
+                            .and_then(|id| self.state.get(id));
+                        self.launched.insert(update.id);
+                        self.in_flight.insert(update.id, self.shots.len());
+                        self.shots.push(Shot {
+                            launch_time: time,
+                            end_time: None,
+                            shooter: shooter.into(),
+                            weapon: Some(object.into()),
+                            weapon_type: object.name().map(String::from),
+                            target: target.map(Participant::from),
+                            impact: None,
+                            outcome: ShotOutcome::Unknown,
+                        });
+                    }
+                } else if let Some(&i) = self.in_flight.get(&update.id) {
+                    let shot = &mut self.shots[i];
+                    if let Some(target) = update.props.iter().find_map(|p| match p {
+                        Property::LockedTarget(id) => self.state.get(*id),
+                        _ => None,
+                    }) {
+                        shot.target = Some(target.into());
+                    }
+                    if shot.weapon_type.is_none() {
+                        shot.weapon_type = object.name().map(String::from);
+                    }
+                }
+
+                for p in &update.props {
+                    match *p {
+                        Property::Health(health) => {
+                            let previous = self.health.insert(update.id, health);
+                            if health <= 0.0 {
+                                self.damage(update.id, Some(KillKind::Destroyed));
+                            } else if previous.is_some_and(|previous| health < previous) {
+                                self.damage(update.id, None);
+                            }
+                        }
+                        Property::Disabled(true) => {
+                            self.damage(update.id, Some(KillKind::Disabled))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Record::Remove(id) => {
+                if let Some(i) = self.in_flight.remove(id) {
+                    let shot = &mut self.shots[i];
+                    shot.end_time = Some(time);
+                    shot.impact = self.state.get(*id).map(|o| o.coords.clone());
+                }
+                self.launched.remove(id);
+                self.health.remove(id);
+                self.killed.remove(id);
+                self.state.apply(record);
+            }
+            Record::Event(event) => match event.kind {
+                EventKind::Destroyed => {
+                    if let Some(id) = event
+                        .params
+                        .first()
+                        .and_then(|p| u64::from_str_radix(p, 16).ok())
+                    {
+                        self.damage(id, Some(KillKind::Destroyed));
+                    }
+                }
+                EventKind::Timeout => self.timeout(&event.params),
+                _ => {}
+            },
+            Record::GlobalProperty(_) | Record::Frame(_) => {
+                self.state.apply(record);
+            }
+        }
+    }
+
+    /// Attribute damages and kills to shots and return the log.
+    pub fn finish(mut self) -> ShotLog {
+        let mut kills = Vec::new();
+        for damage in &self.damages {
+            let shot = find_shot(&self.shots, damage);
+            if let Some(i) = shot {
+                let outcome = &mut self.shots[i].outcome;
+                *outcome = match (damage.kill, *outcome) {
+                    (Some(_), _) | (None, ShotOutcome::Kill) => ShotOutcome::Kill,
+                    (None, _) => ShotOutcome::Hit,
+                };
+            }
+            if let Some(kind) = damage.kill {
+                let shot = shot.map(|i| &self.shots[i]);
+                kills.push(Kill {
+                    time: damage.time,
+                    kind,
+                    victim: damage.victim.clone(),
+                    killer: shot.map(|s| s.shooter.clone()),
+                    weapon: shot.and_then(|s| s.weapon.clone()),
+                    weapon_type: shot.and_then(|s| s.weapon_type.clone()),
+                });
+            }
+        }
+
+        for shot in &mut self.shots {
+            if shot.outcome == ShotOutcome::Unknown && shot.end_time.is_some() {
+                shot.outcome = ShotOutcome::Miss;
+            }
+        }
+
+        ShotLog {
+            shots: self.shots,
+            kills,
+        }
+    }
+
+    fn damage(&mut self, id: u64, kill: Option<KillKind>) {
+        if self.killed.contains(&id) {
+            return;
+        }
+        if kill.is_some() {
+            self.killed.insert(id);
+        }
+        let (victim, position) = match self.state.get(id) {
+            Some(object) => (object.into(), object.coords.clone()),
+            None => (Participant::unknown(id), Coords::default()),
+        };
+        self.damages.push(Damage {
+            time: self.state.time,
+            victim,
+            position,
+            kill,
+        });
+    }
+
+    /// `Timeout` events describe a shot with `key:value` parameters, e.g.
+    /// `0,Event=Timeout|SourceId:507|AmmoType:FOX2|TargetId:201|Outcome:Kill`.
+    fn timeout(&mut self, params: &[String]) {
+        let param = |key: &str| {
+            params
+                .iter()
+                .find_map(|p| p.split_once(':').filter(|(k, _)| *k == key).map(|(_, v)| v))
+        };
+        let id = |key: &str| param(key).and_then(|v| u64::from_str_radix(v, 16).ok());
+        let Some(source) = id("SourceId") else {
+            return;
+        };
+        let participant = |id: u64| {
+            self.state
+                .get(id)
+                .map_or_else(|| Participant::unknown(id), Participant::from)
+        };
+        let target = id("TargetId").map(participant);
+        let time = self.state.time;
+
+        // ends the latest weapon of the source still in flight, if any
+        let in_flight = self
+            .in_flight
+            .iter()
+            .filter(|(_, i)| self.shots[**i].shooter.id == source)
+            .max_by_key(|(_, i)| **i)
+            .map(|(id, i)| (*id, *i));
+        match in_flight {
+            Some((weapon, i)) => {
+                self.in_flight.remove(&weapon);
+                let shot = &mut self.shots[i];
+                shot.end_time = Some(time);
+                shot.impact = self.state.get(weapon).map(|o| o.coords.clone());
+                if target.is_some() {
+                    shot.target = target;
+                }
+            }
+            None => self.shots.push(Shot {
+                launch_time: time,
+                end_time: Some(time),
+                shooter: participant(source),
+                weapon: None,
+                weapon_type: param("AmmoType").map(String::from),
+                target,
+                impact: None,
+                outcome: ShotOutcome::Unknown,
+            }),
+        }
+    }
+}
+
+/// The latest shot at the damaged object, or else the latest shot without target which ended
+/// close to it.
+fn find_shot(shots: &[Shot], damage: &Damage) -> Option<usize> {
+    let in_window = |shot: &Shot| {
+        shot.launch_time <= damage.time
+            && shot
+                .end_time
+                .is_none_or(|end| (end - damage.time).abs() <= ATTRIBUTION_WINDOW)
+    };
+    let aimed = shots.iter().rposition(|shot| {
+        in_window(shot)
+            && shot
+                .target
+                .as_ref()
+                .is_some_and(|t| t.id == damage.victim.id)
+    });
+    aimed.or_else(|| {
+        shots.iter().rposition(|shot| {
+            in_window(shot)
+                && shot.target.is_none()
+                && shot.impact.as_ref().is_some_and(|impact| {
+                    impact
+                        .slant_range(&damage.position)
+                        .is_some_and(|d| d <= HIT_RADIUS)
+                })
+        })
+    })
+}
+
+/// Compute the [`ShotLog`] of a recording.
+pub fn shot_log<'a>(records: impl IntoIterator<Item = &'a Record>) -> ShotLog {
+    let mut collector = ShotCollector::new();
+    for record in records {
+        collector.push(record);
+    }
+    collector.finish()
+}
+
+impl Participant {
+    fn unknown(id: u64) -> Self {
+        Self {
+            id,
+            name: None,
+            call_sign: None,
+            pilot: None,
+            coalition: None,
+        }
+    }
+}
+
+impl From<&ObjectState> for Participant {
+    fn from(object: &ObjectState) -> Self {
+        Self {
+            id: object.id,
+            name: object.name().map(String::from),
+            call_sign: object.call_sign().map(String::from),
+            pilot: object.pilot().map(String::from),
+            coalition: object.coalition().map(String::from),
+        }
+    }
+}
+
+impl ShotOutcome {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ShotOutcome::Kill => "Kill",
+            ShotOutcome::Hit => "Hit",
+            ShotOutcome::Miss => "Miss",
+            ShotOutcome::Unknown => "Unknown",
+        }
+    }
+}
+
+impl KillKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            KillKind::Destroyed => "Destroyed",
+            KillKind::Disabled => "Disabled",
+        }
+    }
+}
+
+/// Id, name, pilot and coalition of an optional participant.
+fn participant_values(p: Option<&Participant>) -> [Value<'_>; 4] {
+    [
+        p.map_or(Value::Text(None), |p| Value::Id(p.id)),
+        Value::Text(p.and_then(|p| p.name.as_deref())),
+        Value::Text(p.and_then(|p| p.pilot.as_deref())),
+        Value::Text(p.and_then(|p| p.coalition.as_deref())),
+    ]
+}
+
+impl Export for Shot {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "launch_time",
+            "end_time",
+            "shooter_id",
+            "shooter_name",
+            "shooter_pilot",
+            "shooter_coalition",
+            "weapon_id",
+            "weapon_type",
+            "target_id",
+            "target_name",
+            "target_pilot",
+            "target_coalition",
+            "outcome",
+        ]
+    }
+
+    fn values(&self) -> Vec<Value<'_>> {
+        let mut values = vec![
+            Value::Number(Some(self.launch_time)),
+            Value::Number(self.end_time),
+        ];
+        values.extend(participant_values(Some(&self.shooter)));
+        values.push(
+            self.weapon
+                .as_ref()
+                .map_or(Value::Text(None), |w| Value::Id(w.id)),
+        );
+        values.push(Value::Text(self.weapon_type.as_deref()));
+        values.extend(participant_values(self.target.as_ref()));
+        values.push(Value::Text(Some(self.outcome.as_str())));
+        values
+    }
+}
+
+impl Export for Kill {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "time",
+            "kind",
+            "victim_id",
+            "victim_name",
+            "victim_pilot",
+            "victim_coalition",
+            "killer_id",
+            "killer_name",
+            "killer_pilot",
+            "killer_coalition",
+            "weapon_id",
+            "weapon_type",
+        ]
+    }
+
+    fn values(&self) -> Vec<Value<'_>> {
+        let mut values = vec![
+            Value::Number(Some(self.time)),
+            Value::Text(Some(self.kind.as_str())),
+        ];
+        values.extend(participant_values(Some(&self.victim)));
+        values.extend(participant_values(self.killer.as_ref()));
+        values.push(
+            self.weapon
+                .as_ref()
+                .map_or(Value::Text(None), |w| Value::Id(w.id)),
+        );
+        values.push(Value::Text(self.weapon_type.as_deref()));
+        values
+    }
+}
+
+impl Export for KillCount {
+    fn columns() -> &'static [&'static str] {
+        &["killer", "victim", "kills"]
+    }
+
+    fn values(&self) -> Vec<Value<'_>> {
+        vec![
+            Value::Text(Some(&self.killer)),
+            Value::Text(Some(&self.victim)),
+            Value::Count(self.kills),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_shot_log() {
+        let records = parse(
+            "FileType=text/acmi/tacview
+FileVersion=2.2
+#0
+a0,T=0|0|5000,Type=Air,Coalition=Blue,Pilot=Viper
+b0,T=0.1|0|5000,Type=Air,Coalition=Red,Pilot=Ivan,Health=1
+c0,T=0|0.1|5000,Type=Air,Coalition=Red,Pilot=Boris,Health=1
+d0,T=0|0|5000,Type=Air,Coalition=Blue,Pilot=Maverick
+#1
+a0,LockedTarget=b0
+a1,T=0|0|5000,Type=Weapon+Missile,Name=AIM-120C,Parent=a0
+#2
+a2,T=0|0|5000,Type=Weapon+Missile,Name=AIM-9X,Parent=d0
+#10
+-a1
+b0,Health=0
+#12
+a2,T=0|0.1|5000
+-a2
+c0,Health=0.5
+#30
+0,Event=Destroyed|c0|
+",
+        )
+        .unwrap();
+
+        let log = shot_log(&records);
+        assert_eq!(log.shots.len(), 2);
+        assert_eq!(log.shots[0].weapon_type.as_deref(), Some("AIM-120C"));
+        assert_eq!(log.shots[0].target.as_ref().map(|t| t.id), Some(0xb0));
+        assert_eq!(log.shots[0].outcome, ShotOutcome::Kill);
+        // no target, attributed by proximity of the impact
+        assert_eq!(log.shots[1].target, None);
+        assert_eq!(log.shots[1].end_time, Some(12.0));
+        assert_eq!(log.shots[1].outcome, ShotOutcome::Hit);
+
+        assert_eq!(log.kills.len(), 2);
+        assert_eq!(log.kills[0].victim.id, 0xb0);
+        assert_eq!(log.kills[0].killer.as_ref().map(|k| k.id), Some(0xa0));
+        // too long after the impact
+        assert_eq!(log.kills[1].victim.id, 0xc0);
+        assert_eq!(log.kills[1].killer, None);
+
+        assert_eq!(
+            log.kills_by_coalition(),
+            [KillCount {
+                killer: "Blue".to_string(),
+                victim: "Red".to_string(),
+                kills: 1
+            }]
+        );
+        assert_eq!(log.kills_by_pilot()[0].victim, "Ivan");
+    }
+}
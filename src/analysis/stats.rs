@@ -0,0 +1,335 @@
+use std::collections::BTreeMap;
+
+use super::{Export, Value};
+use crate::{
+    record::{EventKind, Property, Record, Tag},
+    state::{ObjectState, WorldState},
+};
+
+/// Height above ground above which an object reporting `AGL` is considered airborne, in meters.
+const AIRBORNE_AGL: f64 = 3.0;
+
+/// Flight statistics of an object over its lifetime.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlightStats {
+    pub id: u64,
+    pub name: Option<String>,
+    pub call_sign: Option<String>,
+    pub pilot: Option<String>,
+    pub coalition: Option<String>,
+    /// Time of the frame the object appeared in, in seconds.
+    pub first_seen: f64,
+    /// Time of the frame the object was removed in, or of the last frame of the recording.
+    pub last_seen: f64,
+    /// Distance flown, in meters.
+    pub distance: f64,
+    /// Unit: m
+    pub min_altitude: Option<f64>,
+    /// Unit: m
+    pub max_altitude: Option<f64>,
+    pub max_mach: Option<f64>,
+    /// Unit: m/s
+    pub max_tas: Option<f64>,
+    /// Unit: g
+    pub max_vertical_g: Option<f64>,
+    /// Unit: g
+    pub min_vertical_g: Option<f64>,
+    /// Time spent airborne, in seconds.
+    ///
+    /// Objects are airborne between `TakenOff` and `Landed` events, or while their `AGL` is above
+    /// 3 m. Air objects without either are airborne for their whole lifetime.
+    pub time_airborne: f64,
+    /// Number of objects tagged `Weapon` with this object as `Parent`.
+    pub weapons_launched: usize,
+}
+
+impl FlightStats {
+    fn new(id: u64, time: f64) -> Self {
+        Self {
+            id,
+            name: None,
+            call_sign: None,
+            pilot: None,
+            coalition: None,
+            first_seen: time,
+            last_seen: time,
+            distance: 0.0,
+            min_altitude: None,
+            max_altitude: None,
+            max_mach: None,
+            max_tas: None,
+            max_vertical_g: None,
+            min_vertical_g: None,
+            time_airborne: 0.0,
+            weapons_launched: 0,
+        }
+    }
+
+    /// Time between the object appearing and being removed, in seconds.
+    pub fn lifetime(&self) -> f64 {
+        self.last_seen - self.first_seen
+    }
+}
+
+impl Export for FlightStats {
+    fn columns() -> &'static [&'static str] {
+        &[
+            "id",
+            "name",
+            "call_sign",
+            "pilot",
+            "coalition",
+            "first_seen",
+            "last_seen",
+            "lifetime",
+            "distance",
+            "min_altitude",
+            "max_altitude",
+            "max_mach",
+            "max_tas",
+            "max_vertical_g",
+            "min_vertical_g",
+            "time_airborne",
+            "weapons_launched",
+        ]
+    }
+
+    fn values(&self) -> Vec<Value<'_>> {
+        vec![
+            Value::Id(self.id),
+            Value::Text(self.name.as_deref()),
+            Value::Text(self.call_sign.as_deref()),
+            Value::Text(self.pilot.as_deref()),
+            Value::Text(self.coalition.as_deref()),
+            Value::Number(Some(self.first_seen)),
+            Value::Number(Some(self.last_seen)),
+            Value::Number(Some(self.lifetime())),
+            Value::Number(Some(self.distance)),
+            Value::Number(self.min_altitude),
+            Value::Number(self.max_altitude),
+            Value::Number(self.max_mach),
+            Value::Number(self.max_tas),
+            Value::Number(self.max_vertical_g),
+            Value::Number(self.min_vertical_g),
+            Value::Number(Some(self.time_airborne)),
+            Value::Count(self.weapons_launched),
+        ]
+    }
+}
+
+/// Computes [`FlightStats`] of all objects in a single pass over the records of a recording.
+#[derive(Debug, Default)]
+pub struct StatsCollector {
+    state: WorldState,
+    objects: BTreeMap<u64, Tracker>,
+    finished: Vec<FlightStats>,
+}
+
+#[derive(Debug)]
+struct Tracker {
+    stats: FlightStats,
+    /// Whether the object reported being on the ground or airborne at least once.
+    airborne_known: bool,
+    airborne_since: Option<f64>,
+    /// Whether the object was counted as a weapon launched by its parent.
+    launched: bool,
+}
+
+impl StatsCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, record: &Record) {
+        let time = self.state.time;
+        match record {
+            Record::Update(update) => {
+                let previous = self.state.get(update.id).map(|o| o.coords.clone());
+                self.state.apply(record);
+                let Some(object) = self.state.get(update.id) else {
+                    return;
+                };
+                let tracker = self.objects.entry(update.id).or_insert_with(|| Tracker {
+                    stats: FlightStats::new(update.id, time),
+                    airborne_known: false,
+                    airborne_since: None,
+                    launched: false,
+                });
+                let stats = &mut tracker.stats;
+
+                if update.props.iter().any(|p| matches!(p, Property::T(_))) {
+                    if let Some(previous) = previous {
+                        stats.distance += previous.slant_range(&object.coords).unwrap_or(0.0);
+                    }
+                    if let Some(altitude) = object.coords.altitude {
+                        stats.min_altitude =
+                            Some(stats.min_altitude.map_or(altitude, |v| v.min(altitude)));
+                        stats.max_altitude =
+                            Some(stats.max_altitude.map_or(altitude, |v| v.max(altitude)));
+                    }
+                }
+                for p in &update.props {
+                    match *p {
+                        Property::Mach(v) => stats.max_mach = max(stats.max_mach, v),
+                        Property::TAS(v) => stats.max_tas = max(stats.max_tas, v),
+                        Property::VerticalGForce(v) => {
+                            stats.max_vertical_g = max(stats.max_vertical_g, v);
+                            stats.min_vertical_g =
+                                Some(stats.min_vertical_g.map_or(v, |m| m.min(v)));
+                        }
+                        _ => {}
+                    }
+                }
+                if let Some(agl) = update.props.iter().find_map(|p| match p {
+                    Property::AGL(v) => Some(*v),
+                    _ => None,
+                }) {
+                    tracker.set_airborne(time, agl > AIRBORNE_AGL);
+                }
+
+                if !tracker.launched && object.has_tag(&Tag::Weapon) {
+                    if let Some(parent) = object.parent().and_then(|id| self.objects.get_mut(&id)) {
+                        parent.stats.weapons_launched += 1;
+                        // re-borrow, the parent borrowed `self.objects`
+                        if let Some(tracker) = self.objects.get_mut(&update.id) {
+                            tracker.launched = true;
+                        }
+                    }
+                }
+            }
+            Record::Remove(id) => {
+                if let Some(object) = self.state.apply(record) {
+                    if let Some(tracker) = self.objects.remove(id) {
+                        self.finished.push(tracker.finish(&object, time));
+                    }
+                }
+            }
+            Record::Event(event) => {
+                let airborne = match event.kind {
+                    EventKind::TakenOff => true,
+                    EventKind::Landed => false,
+                    _ => return,
+                };
+                let id = event
+                    .params
+                    .first()
+                    .and_then(|p| u64::from_str_radix(p, 16).ok());
+                if let Some(tracker) = id.and_then(|id| self.objects.get_mut(&id)) {
+                    tracker.set_airborne(time, airborne);
+                }
+            }
+            Record::GlobalProperty(_) | Record::Frame(_) => {
+                self.state.apply(record);
+            }
+        }
+    }
+
+    /// The statistics of all objects, in the order they were removed, followed by the objects
+    /// remaining at the end of the recording by id.
+    pub fn finish(mut self) -> Vec<FlightStats> {
+        let time = self.state.time;
+        for (id, tracker) in std::mem::take(&mut self.objects) {
+            if let Some(object) = self.state.get(id) {
+                self.finished.push(tracker.finish(object, time));
+            }
+        }
+        self.finished
+    }
+}
+
+impl Tracker {
+    fn set_airborne(&mut self, time: f64, airborne: bool) {
+        self.airborne_known = true;
+        match (airborne, self.airborne_since) {
+            (true, None) => self.airborne_since = Some(time),
+            (false, Some(since)) => {
+                self.stats.time_airborne += time - since;
+                self.airborne_since = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(mut self, object: &ObjectState, time: f64) -> FlightStats {
+        self.stats.last_seen = time;
+        if let Some(since) = self.airborne_since {
+            self.stats.time_airborne += time - since;
+        } else if !self.airborne_known && object.has_tag(&Tag::Air) {
+            self.stats.time_airborne = self.stats.lifetime();
+        }
+        self.stats.name = object.name().map(String::from);
+        self.stats.call_sign = object.call_sign().map(String::from);
+        self.stats.pilot = object.pilot().map(String::from);
+        self.stats.coalition = object.coalition().map(String::from);
+        self.stats
+    }
+}
+
+/// Compute the [`FlightStats`] of all objects of a recording.
+pub fn flight_stats<'a>(records: impl IntoIterator<Item = &'a Record>) -> Vec<FlightStats> {
+    let mut collector = StatsCollector::new();
+    for record in records {
+        collector.push(record);
+    }
+    collector.finish()
+}
+
+fn max(current: Option<f64>, v: f64) -> Option<f64> {
+    Some(current.map_or(v, |m| m.max(v)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analysis::write_csv, parse};
+
+    #[test]
+    fn test_flight_stats() {
+        let records = parse(
+            "FileType=text/acmi/tacview
+FileVersion=2.2
+#0
+a0,T=0|0|0,Type=Air+FixedWing,Name=F-16C,Pilot=Viper,AGL=0
+#10
+a0,T=0|0|1000,AGL=1000,TAS=250,Mach=0.8,VerticalGForce=3
+#20
+a0,T=0|0|2000,AGL=2000,VerticalGForce=-1
+b0,T=0|0|2000,Type=Weapon+Missile,Parent=a0
+#30
+-b0
+a0,T=0|0|0,AGL=0
+#40
+",
+        )
+        .unwrap();
+
+        let stats = flight_stats(&records);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].id, 0xb0);
+        assert_eq!(stats[0].lifetime(), 10.0);
+
+        let a0 = &stats[1];
+        assert_eq!(a0.name.as_deref(), Some("F-16C"));
+        assert_eq!(a0.pilot.as_deref(), Some("Viper"));
+        assert_eq!((a0.first_seen, a0.last_seen), (0.0, 40.0));
+        assert_eq!(a0.distance, 4000.0);
+        assert_eq!(
+            (a0.min_altitude, a0.max_altitude),
+            (Some(0.0), Some(2000.0))
+        );
+        assert_eq!((a0.max_mach, a0.max_tas), (Some(0.8), Some(250.0)));
+        assert_eq!(
+            (a0.max_vertical_g, a0.min_vertical_g),
+            (Some(3.0), Some(-1.0))
+        );
+        assert_eq!(a0.time_airborne, 20.0);
+        assert_eq!(a0.weapons_launched, 1);
+
+        let mut csv = Vec::new();
+        write_csv(&stats[1..], &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap().lines().nth(1),
+            Some("a0,F-16C,,Viper,,0,40,40,4000,0,2000,0.8,250,3,-1,20,1")
+        );
+    }
+}
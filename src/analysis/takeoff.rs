@@ -0,0 +1,263 @@
+use std::collections::{HashMap, HashSet};
+
+#[cfg(feature = "bevy")]
+use bevy::prelude::Resource;
+
+use crate::{
+    record::{Coords, Event, EventKind, Property, PropertyKey, PropertyList, Record, Tag},
+    state::WorldState,
+};
+
+/// Detects take-offs and landings of air objects from their `AGL`, altitude, speed and
+/// `LandingGear`.
+///
+/// The height of an object is its `AGL`, or else its altitude above the nearest aerodrome, or
+/// else its altitude above where it was first seen taxiing. Its speed is its `TAS`, `IAS` or
+/// `CAS`, or else derived from its positions. Objects first seen on the ground or in the air do
+/// not generate an event.
+///
+/// The `TacviewPlugin` injects the events into the stream once the detector is inserted as a
+/// resource:
+///
+/// ```rust,ignore
+/// app.init_resource::<TakeoffDetector>();
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "bevy", derive(Resource))]
+pub struct TakeoffDetector {
+    /// Height above which an object on the ground has taken off, in meters.
+    pub takeoff_height: f64,
+    /// Height below which an object in the air has landed, in meters.
+    pub landing_height: f64,
+    /// Speed below which an object first seen without known height is on the ground, in m/s.
+    pub taxi_speed: f64,
+    /// Distance within which an aerodrome is reported in the events, in meters.
+    pub aerodrome_range: f64,
+    objects: HashMap<u64, Track>,
+    aerodromes: HashMap<u64, Aerodrome>,
+}
+
+#[derive(Debug, Clone)]
+struct Track {
+    airborne: Option<bool>,
+    /// Altitude where the object was first seen on the ground.
+    ground_altitude: Option<f64>,
+    position: Coords,
+    time: f64,
+}
+
+#[derive(Debug, Clone)]
+struct Aerodrome {
+    name: Option<String>,
+    position: Coords,
+}
+
+impl Default for TakeoffDetector {
+    fn default() -> Self {
+        Self {
+            takeoff_height: 10.0,
+            landing_height: 3.0,
+            taxi_speed: 30.0,
+            aerodrome_range: 5000.0,
+            objects: HashMap::new(),
+            aerodromes: HashMap::new(),
+        }
+    }
+}
+
+impl TakeoffDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update the state of an object with its absolute coordinates and current properties, returns
+    /// a `TakenOff` or `Landed` event if it took off or landed since the last update.
+    pub fn update(
+        &mut self,
+        id: u64,
+        time: f64,
+        coords: &Coords,
+        props: &PropertyList,
+    ) -> Option<Event> {
+        let Property::Type(tags) = props.get(&PropertyKey::Type)? else {
+            return None;
+        };
+        let name = match props.get(&PropertyKey::Name) {
+            Some(Property::Name(v)) => Some(v.clone()),
+            _ => None,
+        };
+        if tags.contains(&Tag::Aerodrome) {
+            self.aerodromes.insert(
+                id,
+                Aerodrome {
+                    name,
+                    position: coords.clone(),
+                },
+            );
+            return None;
+        }
+        if !tags.contains(&Tag::Air) || tags.contains(&Tag::Weapon) {
+            return None;
+        }
+
+        let aerodrome = self
+            .aerodromes
+            .iter()
+            .filter_map(|(id, a)| Some((id, a, coords.slant_range(&a.position)?)))
+            .filter(|(_, _, d)| *d <= self.aerodrome_range)
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(id, a, _)| (*id, a));
+
+        let track = self.objects.entry(id).or_insert_with(|| Track {
+            airborne: None,
+            ground_altitude: None,
+            position: coords.clone(),
+            time,
+        });
+
+        let mut speed = props
+            .get_f64(&PropertyKey::TAS)
+            .or_else(|| props.get_f64(&PropertyKey::IAS))
+            .or_else(|| props.get_f64(&PropertyKey::CAS));
+        let agl = props.get_f64(&PropertyKey::AGL);
+        let gear_down = props.get_f64(&PropertyKey::LandingGear).map(|v| v > 0.5);
+        if speed.is_none() && time > track.time {
+            speed = track
+                .position
+                .slant_range(coords)
+                .map(|d| d / (time - track.time));
+        }
+        let height = agl
+            .or_else(|| Some(coords.altitude? - aerodrome?.1.position.altitude?))
+            .or_else(|| Some(coords.altitude? - track.ground_altitude?));
+        track.position = coords.clone();
+        track.time = time;
+
+        let airborne = match (track.airborne, height) {
+            (Some(false), Some(h)) => h > self.takeoff_height,
+            (Some(true), Some(h)) => h >= self.landing_height || gear_down == Some(false),
+            (Some(airborne), None) => airborne,
+            (None, Some(h)) => h > self.takeoff_height,
+            (None, None) => match speed {
+                Some(speed) if speed < self.taxi_speed && gear_down != Some(false) => {
+                    track.ground_altitude = coords.altitude;
+                    false
+                }
+                Some(_) => true,
+                None => return None,
+            },
+        };
+        let previous = track.airborne.replace(airborne);
+        if previous.is_none_or(|previous| previous == airborne) {
+            return None;
+        }
+
+        let (kind, verb) = if airborne {
+            (EventKind::TakenOff, "took off from")
+        } else {
+            (EventKind::Landed, "landed at")
+        };
+        let mut params = vec![format!("{id:x}")];
+        let mut text = None;
+        if let Some((aerodrome_id, aerodrome)) = aerodrome {
+            params.push(format!("{aerodrome_id:x}"));
+            if let (Some(name), Some(aerodrome)) = (&name, &aerodrome.name) {
+                text = Some(format!("{name} {verb} {aerodrome}"));
+            }
+        }
+        Some(Event { kind, params, text })
+    }
+
+    pub fn remove(&mut self, id: u64) {
+        self.objects.remove(&id);
+        self.aerodromes.remove(&id);
+    }
+
+    /// Insert `TakenOff` and `Landed` events into a recording, after the update they were detected
+    /// in. Objects which already have such events in the recording are skipped.
+    pub fn apply(&mut self, records: &[Record]) -> Vec<Record> {
+        let manual = records
+            .iter()
+            .filter_map(|r| match r {
+                Record::Event(e) if matches!(e.kind, EventKind::TakenOff | EventKind::Landed) => e
+                    .params
+                    .first()
+                    .and_then(|p| u64::from_str_radix(p, 16).ok()),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+
+        let mut state = WorldState::new();
+        let mut detected = Vec::with_capacity(records.len());
+        for record in records {
+            state.apply(record);
+            detected.push(record.clone());
+            match record {
+                Record::Update(update) if !manual.contains(&update.id) => {
+                    if let Some(object) = state.get(update.id) {
+                        if let Some(event) =
+                            self.update(update.id, state.time, &object.coords, &object.props)
+                        {
+                            detected.push(event.into());
+                        }
+                    }
+                }
+                Record::Remove(id) => self.remove(*id),
+                _ => {}
+            }
+        }
+        detected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_takeoff_landing() {
+        let records = parse(
+            "FileType=text/acmi/tacview
+FileVersion=2.2
+#0
+ff,T=0|0|100,Type=Aerodrome,Name=Nellis
+a0,T=0|0|100,Type=Air+FixedWing,Name=F-16C,IAS=5
+b0,T=0|0|1000,Type=Air+FixedWing,IAS=150
+#10
+a0,T=0|0.001|100,IAS=60
+#20
+a0,T=0|0.01|200,IAS=80
+#30
+a0,T=0|0.5|3000,IAS=150
+#40
+a0,T=0|0.01|102,IAS=70
+",
+        )
+        .unwrap();
+
+        let events = TakeoffDetector::new()
+            .apply(&records)
+            .into_iter()
+            .filter_map(|r| match r {
+                Record::Event(e) => Some(e),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                Event {
+                    kind: EventKind::TakenOff,
+                    params: vec!["a0".to_string(), "ff".to_string()],
+                    text: Some("F-16C took off from Nellis".to_string()),
+                },
+                Event {
+                    kind: EventKind::Landed,
+                    params: vec!["a0".to_string(), "ff".to_string()],
+                    text: Some("F-16C landed at Nellis".to_string()),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,311 @@
+//! Command-line tool to inspect and process ACMI recordings.
+
+use std::{collections::BTreeMap, error::Error, path::PathBuf, process::ExitCode};
+
+use bevy_tacview::{
+    analysis::{self, flight_stats},
+    file::{self, Format},
+    filter::Filter,
+    merge::merge,
+    record::{GlobalProperty, Record, Tag},
+    state::{ObjectState, WorldState},
+    validate::validate,
+};
+use clap::{Parser, Subcommand, ValueEnum};
+
+#[derive(Parser)]
+#[command(
+    name = "tacview",
+    version,
+    about = "Inspect and process Tacview ACMI recordings"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the global properties, the duration and the number of objects by tag.
+    Info { input: PathBuf },
+    /// Check a recording for errors and warnings, exits with 1 if there are errors.
+    Validate { input: PathBuf },
+    /// Extract a time window and a subset of the objects.
+    Cut {
+        input: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Start of the window, in seconds or as `hh:mm:ss`.
+        #[arg(long, value_parser = parse_time)]
+        start: Option<f64>,
+        /// End of the window, in seconds or as `hh:mm:ss`.
+        #[arg(long, value_parser = parse_time)]
+        end: Option<f64>,
+        #[arg(long)]
+        tag: Vec<String>,
+        #[arg(long)]
+        coalition: Vec<String>,
+        #[arg(long)]
+        group: Vec<String>,
+        /// `Name` or `CallSign` of the objects to keep.
+        #[arg(long)]
+        name: Vec<String>,
+        /// Hexadecimal id of the objects to keep.
+        #[arg(long, value_parser = parse_id)]
+        id: Vec<u64>,
+    },
+    /// Merge recordings into a single timeline aligned by their `ReferenceTime`.
+    Merge {
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Convert a recording between plain text and zip compressed ACMI.
+    Convert {
+        input: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Format of the output, by default given by its extension.
+        #[arg(short, long)]
+        format: Option<OutputFormat>,
+    },
+    /// Print the number of records, events and properties.
+    Stats {
+        input: PathBuf,
+        /// Print the flight statistics of every object instead, as CSV or JSON.
+        #[arg(long, value_name = "FORMAT")]
+        objects: Option<ExportFormat>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Zip,
+}
+
+impl From<OutputFormat> for Format {
+    fn from(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Text => Format::Text,
+            OutputFormat::Zip => Format::Zip,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<ExitCode, Box<dyn Error>> {
+    match cli.command {
+        Command::Info { input } => info(&read(&input)?),
+        Command::Validate { input } => {
+            let report = validate(&file::read(&input)?)?;
+            print!("{report}");
+            println!(
+                "{} errors, {} warnings",
+                report.errors().count(),
+                report.warnings().count()
+            );
+            if !report.is_valid() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Cut {
+            input,
+            output,
+            start,
+            end,
+            tag,
+            coalition,
+            group,
+            name,
+            id,
+        } => {
+            let mut filter = Filter::new();
+            if let Some(start) = start {
+                filter = filter.start(start);
+            }
+            if let Some(end) = end {
+                filter = filter.end(end);
+            }
+            filter = tag.iter().fold(filter, |f, v| f.tag(Tag::from(v.as_str())));
+            filter = coalition.into_iter().fold(filter, Filter::coalition);
+            filter = group.into_iter().fold(filter, Filter::group);
+            filter = name.into_iter().fold(filter, Filter::name);
+            filter = id.into_iter().fold(filter, Filter::id);
+            file::write(output, &filter.apply(&read(&input)?)?)?;
+        }
+        Command::Merge { inputs, output } => {
+            let recordings = inputs.iter().map(read).collect::<Result<Vec<_>, _>>()?;
+            file::write(output, &merge(&recordings)?)?;
+        }
+        Command::Convert {
+            input,
+            output,
+            format,
+        } => {
+            let format = format.map_or_else(|| Format::from_path(&output), Format::from);
+            file::write_as(output, format, &read(&input)?)?;
+        }
+        Command::Stats { input, objects } => {
+            let records = read(&input)?;
+            let stdout = std::io::stdout().lock();
+            match objects {
+                Some(ExportFormat::Csv) => analysis::write_csv(&flight_stats(&records), stdout)?,
+                Some(ExportFormat::Json) => analysis::write_json(&flight_stats(&records), stdout)?,
+                None => stats(&records),
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Read the records of a recording, lines which fail to parse are reported and skipped.
+fn read(path: &PathBuf) -> Result<Vec<Record>, Box<dyn Error>> {
+    let text = file::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut parser =
+        bevy_tacview::Parser::new(&text).map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut records = Vec::new();
+    while let Some(record) = parser.next() {
+        match record.and_then(|r| r.to_record()) {
+            Ok(record) => records.push(record),
+            Err(e) => eprintln!(
+                "warning: {}:{}: skipped: {e}",
+                path.display(),
+                parser.line()
+            ),
+        }
+    }
+    Ok(records)
+}
+
+fn info(records: &[Record]) {
+    let mut state = WorldState::new();
+    let mut objects = Vec::new();
+    let (mut first, mut last) = (None, 0.0);
+    for record in records {
+        if let Record::Frame(time) = record {
+            first.get_or_insert(*time);
+            last = *time;
+        }
+        objects.extend(state.apply(record));
+    }
+    objects.extend(state.objects.values().cloned());
+
+    for p in &state.globals {
+        println!("{}", global_line(p));
+    }
+    println!("Duration: {}", format_time(last - first.unwrap_or(0.0)));
+    println!("Objects: {}", objects.len());
+    for (tag, count) in count_by_tag(&objects) {
+        println!("  {tag}: {count}");
+    }
+}
+
+fn stats(records: &[Record]) {
+    let mut frames = Vec::new();
+    let (mut globals, mut updates, mut removes) = (0, 0, 0);
+    let mut events = BTreeMap::<String, usize>::new();
+    let mut props = BTreeMap::<String, usize>::new();
+    let mut ids = std::collections::HashSet::new();
+    for record in records {
+        match record {
+            Record::GlobalProperty(_) => globals += 1,
+            Record::Frame(time) => frames.push(*time),
+            Record::Remove(_) => removes += 1,
+            Record::Event(event) => {
+                *events.entry(event.kind.as_str().to_string()).or_default() += 1
+            }
+            Record::Update(update) => {
+                updates += 1;
+                ids.insert(update.id);
+                for p in &update.props {
+                    let line = p.to_string();
+                    let name = line.split_once('=').map_or(line.as_str(), |(name, _)| name);
+                    *props.entry(name.to_string()).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    println!("Records: {}", records.len());
+    println!("Global properties: {globals}");
+    println!("Frames: {}", frames.len());
+    if frames.len() > 1 {
+        let interval = (frames[frames.len() - 1] - frames[0]) / (frames.len() - 1) as f64;
+        println!("Mean frame interval: {interval:.3}s");
+    }
+    println!("Updates: {updates}");
+    println!("Removals: {removes}");
+    println!("Objects: {}", ids.len());
+    println!("Events: {}", events.values().sum::<usize>());
+    for (kind, count) in events {
+        println!("  {kind}: {count}");
+    }
+    println!("Properties: {}", props.values().sum::<usize>());
+    for (name, count) in props {
+        println!("  {name}: {count}");
+    }
+}
+
+fn count_by_tag(objects: &[ObjectState]) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for tags in objects.iter().filter_map(ObjectState::tags) {
+        for tag in tags {
+            *counts.entry(tag.as_str().to_string()).or_default() += 1;
+        }
+    }
+    counts
+}
+
+/// A global property as `Name: Value`.
+fn global_line(p: &GlobalProperty) -> String {
+    let line = p.to_string();
+    let line = line.strip_prefix("0,").unwrap_or(&line);
+    match line.split_once('=') {
+        Some((name, value)) => format!("{name}: {value}"),
+        None => line.to_string(),
+    }
+}
+
+fn format_time(seconds: f64) -> String {
+    let seconds = seconds.max(0.0);
+    let minutes = (seconds / 60.0) as u64;
+    format!(
+        "{:02}:{:02}:{:05.2}",
+        minutes / 60,
+        minutes % 60,
+        seconds % 60.0
+    )
+}
+
+/// Parse seconds, `mm:ss` or `hh:mm:ss`.
+fn parse_time(s: &str) -> Result<f64, String> {
+    s.rsplit(':')
+        .enumerate()
+        .try_fold(0.0, |total, (i, part)| {
+            let v = part.parse::<f64>().ok().filter(|_| i < 3)?;
+            Some(total + v * 60f64.powi(i as i32))
+        })
+        .ok_or_else(|| format!("invalid time `{s}`, expected seconds or hh:mm:ss"))
+}
+
+fn parse_id(s: &str) -> Result<u64, String> {
+    u64::from_str_radix(s, 16).map_err(|e| format!("invalid id `{s}`: {e}"))
+}
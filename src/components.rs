@@ -0,0 +1,512 @@
+//! Components for common properties, gathered into the [`PropertyList`] of their entity.
+//!
+//! This allows queries and change detection on single properties, e.g. all entities with a
+//! [`Health`] under 0.5. The `Name` property is gathered from Bevy's [`Name`] component and the
+//! `Color` property from the [`Color`] component. Entities need a [`PropertyList`] for their
+//! components to be gathered.
+//!
+//! [`PropertyList`]: crate::record::PropertyList
+
+#![allow(clippy::upper_case_acronyms)]
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::{
+    record::{Color, Property, Tag},
+    systems::{sync_component, TacviewSet},
+};
+
+pub use bevy_tacview_derive::TacviewObject;
+
+/// A component written as properties of the object of its entity, usually derived with
+/// [`TacviewObject`].
+pub trait TacviewComponent: Component {
+    fn to_properties(&self) -> Vec<Property>;
+
+    /// Read a received or parsed property into the component, returns whether it was used.
+    fn apply_property(&mut self, property: &Property) -> bool;
+
+    /// Read all properties used by the component.
+    fn apply_properties<'a>(&mut self, props: impl IntoIterator<Item = &'a Property>) {
+        for property in props {
+            self.apply_property(property);
+        }
+    }
+}
+
+/// Conversion of a field to and from the value of a property, used by [`TacviewObject`].
+pub trait PropertyValue<V>: Sized {
+    fn to_value(&self) -> V;
+
+    /// The field of a value, `None` if the field cannot hold it.
+    fn from_value(value: V) -> Option<Self>;
+}
+
+impl<V: Clone> PropertyValue<V> for V {
+    fn to_value(&self) -> V {
+        self.clone()
+    }
+
+    fn from_value(value: V) -> Option<Self> {
+        Some(value)
+    }
+}
+
+impl PropertyValue<f64> for f32 {
+    fn to_value(&self) -> f64 {
+        f64::from(*self)
+    }
+
+    fn from_value(value: f64) -> Option<Self> {
+        Some(value as f32)
+    }
+}
+
+/// Object ids of entities, as in `Parent` or `LockedTarget`. Ids which are not the bits of an
+/// entity, like the ids of a recording, have no entity.
+impl PropertyValue<u64> for Entity {
+    fn to_value(&self) -> u64 {
+        self.to_bits()
+    }
+
+    fn from_value(value: u64) -> Option<Self> {
+        Entity::try_from_bits(value).ok()
+    }
+}
+
+/// Register components to gather into the properties of their entity.
+pub trait TacviewAppExt {
+    fn add_tacview_component<T: TacviewComponent>(&mut self) -> &mut Self;
+}
+
+impl TacviewAppExt for App {
+    fn add_tacview_component<T: TacviewComponent>(&mut self) -> &mut Self {
+        self.add_systems(Update, sync_component::<T>.in_set(TacviewSet::Gather))
+    }
+}
+
+#[derive(Component, Reflect, Debug, Clone, PartialEq, Default)]
+#[reflect(Component)]
+pub struct CallSign(pub String);
+
+#[derive(Component, Reflect, Debug, Clone, PartialEq, Default)]
+#[reflect(Component)]
+pub struct Pilot(pub String);
+
+#[derive(Component, Reflect, Debug, Clone, PartialEq, Default)]
+#[reflect(Component)]
+pub struct Coalition(pub String);
+
+/// Tags of the `Type` property.
+#[derive(Component, Reflect, Debug, Clone, PartialEq, Default)]
+#[reflect_value(Component, Debug, PartialEq)]
+pub struct Tags(pub HashSet<Tag>);
+
+impl Tags {
+    pub fn contains(&self, tag: &Tag) -> bool {
+        self.0.contains(tag)
+    }
+}
+
+impl<const N: usize> From<[Tag; N]> for Tags {
+    fn from(tags: [Tag; N]) -> Self {
+        Self(HashSet::from(tags))
+    }
+}
+
+/// Unit: ratio
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[reflect(Component)]
+pub struct Health(pub f64);
+
+/// Indicated airspeed. Unit: m/s
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[reflect(Component)]
+pub struct IAS(pub f64);
+
+/// Calibrated airspeed. Unit: m/s
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[reflect(Component)]
+pub struct CAS(pub f64);
+
+/// True airspeed. Unit: m/s
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[reflect(Component)]
+pub struct TAS(pub f64);
+
+/// Unit: mach
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[reflect(Component)]
+pub struct Mach(pub f64);
+
+/// Positions of the control surfaces, unset ones are not written.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Default)]
+#[reflect(Component)]
+pub struct ControlSurfaces {
+    /// Unit: ratio
+    pub aileron_left: Option<f64>,
+    /// Unit: ratio
+    pub aileron_right: Option<f64>,
+    /// Unit: ratio
+    pub elevator: Option<f64>,
+    /// Unit: ratio
+    pub rudder: Option<f64>,
+    /// Unit: ratio
+    pub flaps: Option<f64>,
+    /// Unit: ratio
+    pub air_brakes: Option<f64>,
+}
+
+/// State of the radar, unset values are not written.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Default)]
+#[reflect(Component)]
+pub struct Radar {
+    /// 0 is off, 1 is on.
+    pub mode: Option<f64>,
+    /// Unit: deg
+    pub azimuth: Option<f64>,
+    /// Unit: deg
+    pub elevation: Option<f64>,
+    /// Unit: deg
+    pub roll: Option<f64>,
+    /// Unit: m
+    pub range: Option<f64>,
+    /// Unit: deg
+    pub horizontal_beamwidth: Option<f64>,
+    /// Unit: deg
+    pub vertical_beamwidth: Option<f64>,
+}
+
+impl TacviewComponent for Name {
+    fn to_properties(&self) -> Vec<Property> {
+        vec![Property::Name(self.as_str().to_string())]
+    }
+
+    fn apply_property(&mut self, property: &Property) -> bool {
+        match property {
+            Property::Name(v) => self.set(v.clone()),
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl TacviewComponent for Color {
+    fn to_properties(&self) -> Vec<Property> {
+        vec![Property::Color(self.clone())]
+    }
+
+    fn apply_property(&mut self, property: &Property) -> bool {
+        match property {
+            Property::Color(v) => *self = v.clone(),
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl TacviewComponent for CallSign {
+    fn to_properties(&self) -> Vec<Property> {
+        vec![Property::CallSign(self.0.clone())]
+    }
+
+    fn apply_property(&mut self, property: &Property) -> bool {
+        match property {
+            Property::CallSign(v) => self.0 = v.clone(),
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl TacviewComponent for Pilot {
+    fn to_properties(&self) -> Vec<Property> {
+        vec![Property::Pilot(self.0.clone())]
+    }
+
+    fn apply_property(&mut self, property: &Property) -> bool {
+        match property {
+            Property::Pilot(v) => self.0 = v.clone(),
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl TacviewComponent for Coalition {
+    fn to_properties(&self) -> Vec<Property> {
+        vec![Property::Coalition(self.0.clone())]
+    }
+
+    fn apply_property(&mut self, property: &Property) -> bool {
+        match property {
+            Property::Coalition(v) => self.0 = v.clone(),
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl TacviewComponent for Tags {
+    fn to_properties(&self) -> Vec<Property> {
+        vec![Property::Type(self.0.clone())]
+    }
+
+    fn apply_property(&mut self, property: &Property) -> bool {
+        match property {
+            Property::Type(v) => self.0 = v.clone(),
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl TacviewComponent for Health {
+    fn to_properties(&self) -> Vec<Property> {
+        vec![Property::Health(self.0)]
+    }
+
+    fn apply_property(&mut self, property: &Property) -> bool {
+        match property {
+            Property::Health(v) => self.0 = *v,
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl TacviewComponent for IAS {
+    fn to_properties(&self) -> Vec<Property> {
+        vec![Property::IAS(self.0)]
+    }
+
+    fn apply_property(&mut self, property: &Property) -> bool {
+        match property {
+            Property::IAS(v) => self.0 = *v,
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl TacviewComponent for CAS {
+    fn to_properties(&self) -> Vec<Property> {
+        vec![Property::CAS(self.0)]
+    }
+
+    fn apply_property(&mut self, property: &Property) -> bool {
+        match property {
+            Property::CAS(v) => self.0 = *v,
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl TacviewComponent for TAS {
+    fn to_properties(&self) -> Vec<Property> {
+        vec![Property::TAS(self.0)]
+    }
+
+    fn apply_property(&mut self, property: &Property) -> bool {
+        match property {
+            Property::TAS(v) => self.0 = *v,
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl TacviewComponent for Mach {
+    fn to_properties(&self) -> Vec<Property> {
+        vec![Property::Mach(self.0)]
+    }
+
+    fn apply_property(&mut self, property: &Property) -> bool {
+        match property {
+            Property::Mach(v) => self.0 = *v,
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl TacviewComponent for ControlSurfaces {
+    fn to_properties(&self) -> Vec<Property> {
+        [
+            self.aileron_left.map(Property::AileronLeft),
+            self.aileron_right.map(Property::AileronRight),
+            self.elevator.map(Property::Elevator),
+            self.rudder.map(Property::Rudder),
+            self.flaps.map(Property::Flaps),
+            self.air_brakes.map(Property::AirBrakes),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    fn apply_property(&mut self, property: &Property) -> bool {
+        match *property {
+            Property::AileronLeft(v) => self.aileron_left = Some(v),
+            Property::AileronRight(v) => self.aileron_right = Some(v),
+            Property::Elevator(v) => self.elevator = Some(v),
+            Property::Rudder(v) => self.rudder = Some(v),
+            Property::Flaps(v) => self.flaps = Some(v),
+            Property::AirBrakes(v) => self.air_brakes = Some(v),
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl TacviewComponent for Radar {
+    fn to_properties(&self) -> Vec<Property> {
+        [
+            self.mode.map(Property::RadarMode),
+            self.azimuth.map(Property::RadarAzimuth),
+            self.elevation.map(Property::RadarElevation),
+            self.roll.map(Property::RadarRoll),
+            self.range.map(Property::RadarRange),
+            self.horizontal_beamwidth
+                .map(Property::RadarHorizontalBeamwidth),
+            self.vertical_beamwidth
+                .map(Property::RadarVerticalBeamwidth),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    fn apply_property(&mut self, property: &Property) -> bool {
+        match *property {
+            Property::RadarMode(v) => self.mode = Some(v),
+            Property::RadarAzimuth(v) => self.azimuth = Some(v),
+            Property::RadarElevation(v) => self.elevation = Some(v),
+            Property::RadarRoll(v) => self.roll = Some(v),
+            Property::RadarRange(v) => self.range = Some(v),
+            Property::RadarHorizontalBeamwidth(v) => self.horizontal_beamwidth = Some(v),
+            Property::RadarVerticalBeamwidth(v) => self.vertical_beamwidth = Some(v),
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Register the components of this module.
+pub(crate) fn add_components(app: &mut App) {
+    app.register_type::<CallSign>()
+        .register_type::<Pilot>()
+        .register_type::<Coalition>()
+        .register_type::<Tags>()
+        .register_type::<Health>()
+        .register_type::<IAS>()
+        .register_type::<CAS>()
+        .register_type::<TAS>()
+        .register_type::<Mach>()
+        .register_type::<ControlSurfaces>()
+        .register_type::<Radar>()
+        .add_tacview_component::<Name>()
+        .add_tacview_component::<Color>()
+        .add_tacview_component::<CallSign>()
+        .add_tacview_component::<Pilot>()
+        .add_tacview_component::<Coalition>()
+        .add_tacview_component::<Tags>()
+        .add_tacview_component::<Health>()
+        .add_tacview_component::<IAS>()
+        .add_tacview_component::<CAS>()
+        .add_tacview_component::<TAS>()
+        .add_tacview_component::<Mach>()
+        .add_tacview_component::<ControlSurfaces>()
+        .add_tacview_component::<Radar>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::{Coords, PropertyKey, PropertyList};
+    use crate::systems::ObjectNeedSync;
+    use crate::test_utils::test_app;
+
+    #[derive(Component, TacviewObject)]
+    struct Aircraft {
+        #[tacview(CallSign)]
+        callsign: String,
+        #[tacview(FuelWeight(2))]
+        fuel_kg: f32,
+        #[tacview(IAS, unit = "kt")]
+        speed: f64,
+        #[tacview(LockedTarget)]
+        target: Option<Entity>,
+        #[tacview(FocusedTarget)]
+        focused: u64,
+        #[tacview(Parent)]
+        parent: Entity,
+    }
+
+    #[test]
+    fn test_derive() {
+        let mut aircraft = Aircraft {
+            callsign: "Viper 1-1".to_string(),
+            fuel_kg: 1000.0,
+            speed: 300.0,
+            target: None,
+            focused: 0,
+            parent: Entity::PLACEHOLDER,
+        };
+        let props = aircraft.to_properties();
+        assert_eq!(props.len(), 5);
+        assert_eq!(props[0], Property::CallSign("Viper 1-1".to_string()));
+        assert_eq!(props[1], Property::FuelWeight(2, 1000.0));
+        assert!(matches!(props[2], Property::IAS(v) if (v - 154.333).abs() < 1e-3));
+
+        let target = Entity::from_raw(7);
+        aircraft.apply_properties(&[
+            Property::IAS(100.0),
+            Property::FuelWeight(1, 1.0),
+            Property::LockedTarget(target.to_bits()),
+        ]);
+        assert!((aircraft.speed - 194.384).abs() < 1e-3);
+        assert_eq!(aircraft.fuel_kg, 1000.0);
+        assert_eq!(aircraft.target, Some(target));
+        assert!(!aircraft.apply_property(&Property::Pilot("Maverick".to_string())));
+
+        // ids of a recording are not entities
+        aircraft.apply_properties(&[
+            Property::LockedTarget(0x102),
+            Property::FocusedTarget(0x102),
+            Property::Parent(0x102),
+        ]);
+        assert_eq!(aircraft.target, None);
+        assert_eq!(aircraft.focused, 0x102);
+        assert_eq!(aircraft.parent, Entity::PLACEHOLDER);
+    }
+
+    #[test]
+    fn test_sync_component() {
+        let mut app = test_app();
+        let object = app
+            .world
+            .spawn((Coords::default(), PropertyList::new(), Health(1.0)))
+            .id();
+        app.update();
+        assert_eq!(app.world.get::<PropertyList>(object).unwrap().len(), 1);
+        app.world.entity_mut(object).remove::<ObjectNeedSync>();
+
+        // a component changed to the same value is not synced
+        app.world.get_mut::<Health>(object).unwrap().0 = 1.0;
+        app.update();
+        assert!(app.world.get::<ObjectNeedSync>(object).is_none());
+
+        app.world.get_mut::<Health>(object).unwrap().0 = 0.5;
+        app.update();
+        let props = app.world.get::<PropertyList>(object).unwrap();
+        assert_eq!(props.get_f64(&PropertyKey::Health), Some(0.5));
+        assert!(matches!(
+            app.world.get::<ObjectNeedSync>(object),
+            Some(ObjectNeedSync::Update)
+        ));
+    }
+}
@@ -0,0 +1,220 @@
+//! Diagnostics of the telemetry stream, shown by `LogDiagnosticsPlugin`.
+
+use bevy::diagnostic::{
+    Diagnostic, DiagnosticMeasurement, DiagnosticPath, DiagnosticsStore, RegisterDiagnostic,
+};
+use bevy::prelude::*;
+use bevy::utils::{Duration, HashMap, HashSet, Instant};
+
+use crate::systems::{Lagging, PeerWriter};
+use crate::TacviewSet;
+
+/// Adds diagnostics of the peers, objects synced, throughput, dropped frames and serialization time
+/// of the [`TacviewPlugin`](crate::TacviewPlugin).
+///
+/// Records and bytes written per second are measured for every peer under
+/// [`TacviewDiagnosticsPlugin::peer_records_per_second`] and
+/// [`TacviewDiagnosticsPlugin::peer_bytes_per_second`], and summed over every peer. The diagnostics
+/// of a disconnected peer are left at 0.
+#[derive(Default)]
+pub struct TacviewDiagnosticsPlugin;
+
+impl Plugin for TacviewDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SyncStats>()
+            .register_diagnostic(Diagnostic::new(Self::PEERS).with_smoothing_factor(0.0))
+            .register_diagnostic(Diagnostic::new(Self::PEERS_LAGGING).with_smoothing_factor(0.0))
+            .register_diagnostic(Diagnostic::new(Self::OBJECTS_SYNCED))
+            .register_diagnostic(Diagnostic::new(Self::RECORDS_PER_SECOND))
+            .register_diagnostic(Diagnostic::new(Self::BYTES_PER_SECOND).with_suffix("B/s"))
+            .register_diagnostic(Diagnostic::new(Self::FRAMES_DROPPED))
+            .register_diagnostic(Diagnostic::new(Self::SERIALIZATION_TIME).with_suffix("ms"))
+            .register_diagnostic(
+                Diagnostic::new(Self::RECORDER_FILE_SIZE)
+                    .with_suffix("B")
+                    .with_smoothing_factor(0.0),
+            )
+            .add_systems(Update, Self::diagnostic_system.after(TacviewSet::Sync));
+    }
+}
+
+impl TacviewDiagnosticsPlugin {
+    /// Connected peers.
+    pub const PEERS: DiagnosticPath = DiagnosticPath::const_new("tacview/peers");
+    /// Peers which fell behind and skip frames until they caught up.
+    pub const PEERS_LAGGING: DiagnosticPath = DiagnosticPath::const_new("tacview/peers_lagging");
+    /// Objects synced in the frame.
+    pub const OBJECTS_SYNCED: DiagnosticPath = DiagnosticPath::const_new("tacview/objects_synced");
+    /// Records written per second to all peers.
+    pub const RECORDS_PER_SECOND: DiagnosticPath =
+        DiagnosticPath::const_new("tacview/records_per_second");
+    /// Bytes written per second to all peers.
+    pub const BYTES_PER_SECOND: DiagnosticPath =
+        DiagnosticPath::const_new("tacview/bytes_per_second");
+    /// Frames dropped for lagging peers.
+    pub const FRAMES_DROPPED: DiagnosticPath = DiagnosticPath::const_new("tacview/frames_dropped");
+    /// Time spent writing the frames of every peer.
+    pub const SERIALIZATION_TIME: DiagnosticPath =
+        DiagnosticPath::const_new("tacview/serialization_time");
+    /// Size of the recorded file.
+    pub const RECORDER_FILE_SIZE: DiagnosticPath =
+        DiagnosticPath::const_new("tacview/recorder_file_size");
+
+    /// Records written per second to the `peer`.
+    pub fn peer_records_per_second(peer: Entity) -> DiagnosticPath {
+        DiagnosticPath::new(format!("tacview/peers/{peer:?}/records_per_second"))
+    }
+
+    /// Bytes written per second to the `peer`.
+    pub fn peer_bytes_per_second(peer: Entity) -> DiagnosticPath {
+        DiagnosticPath::new(format!("tacview/peers/{peer:?}/bytes_per_second"))
+    }
+
+    fn diagnostic_system(
+        mut store: ResMut<DiagnosticsStore>,
+        mut stats: ResMut<SyncStats>,
+        time: Res<Time<Real>>,
+        q_peers: Query<Entity, With<PeerWriter>>,
+        q_lagging: Query<(), With<Lagging>>,
+        mut known_peers: Local<HashSet<Entity>>,
+    ) {
+        let now = Instant::now();
+        let delta_seconds = time.delta_seconds_f64();
+
+        // diagnostics cannot be removed, those of disconnected peers are left at 0
+        let disconnected = known_peers
+            .iter()
+            .filter(|peer| !q_peers.contains(**peer))
+            .copied()
+            .collect::<Vec<_>>();
+        for peer in disconnected {
+            known_peers.remove(&peer);
+            stats.peers.insert(peer, PeerThroughput::default());
+        }
+        for peer in q_peers.iter() {
+            if known_peers.insert(peer) {
+                store.add(Diagnostic::new(Self::peer_records_per_second(peer)));
+                store.add(Diagnostic::new(Self::peer_bytes_per_second(peer)).with_suffix("B/s"));
+            }
+            stats.peers.entry(peer).or_default();
+        }
+
+        let mut measure = |path: &DiagnosticPath, value: f64| {
+            if let Some(diagnostic) = store.get_mut(path).filter(|d| d.is_enabled) {
+                diagnostic.add_measurement(DiagnosticMeasurement { time: now, value });
+            }
+        };
+        measure(&Self::PEERS, q_peers.iter().len() as f64);
+        measure(&Self::PEERS_LAGGING, q_lagging.iter().len() as f64);
+        measure(&Self::OBJECTS_SYNCED, stats.objects as f64);
+        measure(&Self::FRAMES_DROPPED, stats.frames_dropped as f64);
+        measure(
+            &Self::SERIALIZATION_TIME,
+            stats.serialization.as_secs_f64() * 1000.0,
+        );
+        if let Some(size) = stats.recorder_size {
+            measure(&Self::RECORDER_FILE_SIZE, size as f64);
+        }
+        if delta_seconds > 0.0 {
+            let (records, bytes) = stats.peers.values().fold((0, 0), |(records, bytes), peer| {
+                (records + peer.records, bytes + peer.bytes)
+            });
+            measure(&Self::RECORDS_PER_SECOND, records as f64 / delta_seconds);
+            measure(&Self::BYTES_PER_SECOND, bytes as f64 / delta_seconds);
+            for (peer, throughput) in &stats.peers {
+                measure(
+                    &Self::peer_records_per_second(*peer),
+                    throughput.records as f64 / delta_seconds,
+                );
+                measure(
+                    &Self::peer_bytes_per_second(*peer),
+                    throughput.bytes as f64 / delta_seconds,
+                );
+            }
+        }
+
+        stats.clear();
+    }
+}
+
+/// What was synced in the current frame, only gathered with the [`TacviewDiagnosticsPlugin`].
+#[derive(Resource, Debug, Default)]
+pub(crate) struct SyncStats {
+    pub(crate) objects: usize,
+    /// What was written to every peer.
+    pub(crate) peers: HashMap<Entity, PeerThroughput>,
+    pub(crate) frames_dropped: usize,
+    pub(crate) serialization: Duration,
+    /// Size of the files recorded by sinks.
+    pub(crate) recorder_size: Option<u64>,
+}
+
+/// What was written to a peer in the current frame.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct PeerThroughput {
+    pub(crate) records: usize,
+    pub(crate) bytes: usize,
+}
+
+impl SyncStats {
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::Property;
+    use crate::systems::ObjectNeedSync;
+    use crate::test_utils::{spawn_memory_peer, spawn_object, test_app, FRAME_TIME};
+
+    #[test]
+    fn test_diagnostics() {
+        let mut app = test_app();
+        app.add_plugins(TacviewDiagnosticsPlugin);
+        let (peers, memory): (Vec<_>, Vec<_>) =
+            (0..2).map(|_| spawn_memory_peer(&mut app.world)).unzip();
+        let objects = [(); 2].map(|_| spawn_object(&mut app.world, vec![Property::Health(0.5)]));
+        app.update();
+        memory[0].take();
+
+        app.world
+            .entity_mut(objects[0])
+            .insert(ObjectNeedSync::Update);
+        app.update();
+        let bytes = memory[0].take().len();
+        let store = app.world.resource::<DiagnosticsStore>();
+        let value = |path: &DiagnosticPath| store.get(path).and_then(Diagnostic::value);
+        let delta = FRAME_TIME.as_secs_f64();
+        assert_eq!(value(&TacviewDiagnosticsPlugin::PEERS), Some(2.0));
+        assert_eq!(value(&TacviewDiagnosticsPlugin::OBJECTS_SYNCED), Some(1.0));
+        assert_eq!(
+            value(&TacviewDiagnosticsPlugin::peer_bytes_per_second(peers[0])),
+            Some(bytes as f64 / delta)
+        );
+        assert_eq!(
+            value(&TacviewDiagnosticsPlugin::peer_records_per_second(peers[0])),
+            Some(1.0 / delta)
+        );
+        // both peers got the same frame
+        assert_eq!(
+            value(&TacviewDiagnosticsPlugin::BYTES_PER_SECOND),
+            Some(2.0 * bytes as f64 / delta)
+        );
+
+        app.world.despawn(peers[0]);
+        app.world
+            .entity_mut(objects[1])
+            .insert(ObjectNeedSync::Update);
+        app.update();
+        let store = app.world.resource::<DiagnosticsStore>();
+        let value = |path: &DiagnosticPath| store.get(path).and_then(Diagnostic::value);
+        assert_eq!(value(&TacviewDiagnosticsPlugin::PEERS), Some(1.0));
+        assert_eq!(
+            value(&TacviewDiagnosticsPlugin::peer_bytes_per_second(peers[0])),
+            Some(0.0)
+        );
+        assert!(value(&TacviewDiagnosticsPlugin::peer_bytes_per_second(peers[1])).unwrap() > 0.0);
+    }
+}
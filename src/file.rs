@@ -0,0 +1,143 @@
+//! Reading and writing of ACMI files, plain text (`.txt.acmi`) or zip compressed (`.zip.acmi`).
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use crate::{record::Record, ParseError, Writer};
+
+/// Storage format of an ACMI file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Plain ACMI text.
+    Text,
+    /// A zip archive containing a single ACMI text file.
+    #[cfg(feature = "zip")]
+    Zip,
+}
+
+impl Format {
+    /// The format of a file by its extension, `.zip.acmi` and `.zip` are zip compressed.
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        #[cfg(feature = "zip")]
+        {
+            let name = path.as_ref().to_string_lossy().to_lowercase();
+            if name.ends_with(".zip.acmi") || name.ends_with(".zip") {
+                return Self::Zip;
+            }
+        }
+        #[cfg(not(feature = "zip"))]
+        let _ = path;
+        Self::Text
+    }
+}
+
+/// Read the ACMI text of a file, zip compressed files are detected by their content.
+pub fn read(path: impl AsRef<Path>) -> Result<String, ParseError> {
+    let bytes = std::fs::read(path)?;
+
+    #[cfg(feature = "zip")]
+    if bytes.starts_with(b"PK\x03\x04") {
+        use std::io::Read;
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))?;
+        let mut text = String::new();
+        archive.by_index(0)?.read_to_string(&mut text)?;
+        return Ok(text);
+    }
+
+    Ok(String::from_utf8(bytes).map_err(|e| e.utf8_error())?)
+}
+
+/// Write records to a file in the format given by its extension, see [`Format::from_path`].
+pub fn write(path: impl AsRef<Path>, records: &[Record]) -> Result<(), ParseError> {
+    let format = Format::from_path(&path);
+    write_as(path, format, records)
+}
+
+pub fn write_as(
+    path: impl AsRef<Path>,
+    format: Format,
+    records: &[Record],
+) -> Result<(), ParseError> {
+    let path = path.as_ref();
+    let file = BufWriter::new(File::create(path)?);
+
+    match format {
+        Format::Text => write_records(file, records)?.flush()?,
+        #[cfg(feature = "zip")]
+        Format::Zip => {
+            // Tacview names the archived file after the archive, e.g. `a.zip.acmi` contains
+            // `a.txt.acmi`
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default();
+            let stem = name
+                .strip_suffix(".zip.acmi")
+                .or_else(|| name.strip_suffix(".zip"))
+                .unwrap_or(&name);
+
+            let mut zip = zip::ZipWriter::new(file);
+            let options = zip::write::FileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated);
+            zip.start_file(format!("{stem}.txt.acmi"), options)?;
+            write_records(&mut zip, records)?;
+            zip.finish()?.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_records<W: Write>(wr: W, records: &[Record]) -> Result<W, ParseError> {
+    let mut writer = Writer::new(wr)?;
+    for record in records {
+        writer.write_record(record)?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parse,
+        record::{GlobalProperty, Property, Update},
+    };
+
+    #[test]
+    fn test_roundtrip() {
+        let records = vec![
+            GlobalProperty::Title("Test".to_string()).into(),
+            Record::Frame(1.0),
+            Update {
+                id: 0xa0,
+                props: vec![Property::Name("F-16C".to_string())],
+            }
+            .into(),
+        ];
+
+        // unique to this run, tests of concurrent runs must not share files
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let name = format!("bevy_tacview_test_roundtrip_{}_{stamp}", std::process::id());
+        let dir = std::env::temp_dir();
+        let paths = [
+            dir.join(format!("{name}.txt.acmi")),
+            #[cfg(feature = "zip")]
+            dir.join(format!("{name}.zip.acmi")),
+        ];
+
+        for path in paths {
+            write(&path, &records).unwrap();
+            let text = read(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(parse(&text).unwrap(), records);
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! Extraction of a time window and a subset of objects from a recording.
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, SecondsFormat};
+
+use crate::{
+    record::{Coords, Event, GlobalProperty, Property, Record, Tag, Update},
+    state::{ObjectState, WorldState},
+    ParseError,
+};
+
+/// Filters the records of a recording.
+///
+/// Objects must match every kind of criterion which is set, and at least one value of each. For
+/// example `Filter::new().tag(Tag::Air).coalition("Blue")` keeps the air objects of the blue
+/// coalition. References (`Parent`, `Next`, `FocusedTarget`, `LockedTarget`) to dropped objects
+/// are removed, as are events referencing them.
+///
+/// When a start time is set, frames are re-based so that the window starts at frame `0`,
+/// `ReferenceTime` is moved accordingly and the state of all kept objects at the start time is
+/// written in the first frame.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    start: Option<f64>,
+    end: Option<f64>,
+    tags: Vec<Tag>,
+    coalitions: Vec<String>,
+    groups: Vec<String>,
+    names: Vec<String>,
+    ids: Vec<u64>,
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep frames between `start` and `end`, in seconds since `ReferenceTime`.
+    pub fn time_range(self, start: f64, end: f64) -> Self {
+        self.start(start).end(end)
+    }
+
+    pub fn start(mut self, start: f64) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    pub fn end(mut self, end: f64) -> Self {
+        self.end = Some(end);
+        self
+    }
+
+    pub fn tag(mut self, tag: Tag) -> Self {
+        self.tags.push(tag);
+        self
+    }
+
+    pub fn coalition(mut self, coalition: impl Into<String>) -> Self {
+        self.coalitions.push(coalition.into());
+        self
+    }
+
+    pub fn group(mut self, group: impl Into<String>) -> Self {
+        self.groups.push(group.into());
+        self
+    }
+
+    /// Keep objects with the given `Name` or `CallSign`.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.names.push(name.into());
+        self
+    }
+
+    pub fn id(mut self, id: u64) -> Self {
+        self.ids.push(id);
+        self
+    }
+
+    /// Whether an object matches the object criteria of the filter.
+    pub fn matches(&self, object: &ObjectState) -> bool {
+        (self.ids.is_empty() || self.ids.contains(&object.id))
+            && (self.tags.is_empty() || self.tags.iter().any(|tag| object.has_tag(tag)))
+            && (self.coalitions.is_empty()
+                || object
+                    .coalition()
+                    .is_some_and(|c| self.coalitions.iter().any(|v| v == c)))
+            && (self.groups.is_empty()
+                || object
+                    .group()
+                    .is_some_and(|g| self.groups.iter().any(|v| v == g)))
+            && (self.names.is_empty()
+                || self.names.iter().any(|v| {
+                    object.name() == Some(v.as_str()) || object.call_sign() == Some(v.as_str())
+                }))
+    }
+
+    pub fn apply(&self, records: &[Record]) -> Result<Vec<Record>, ParseError> {
+        // objects are matched on the last state they reach, as properties like `Coalition` may be
+        // set after the object appeared
+        let mut state = WorldState::new();
+        let mut all = HashSet::new();
+        let mut kept = HashSet::new();
+        for record in records {
+            if let Record::Update(update) = record {
+                all.insert(update.id);
+            }
+            if let Some(object) = state.apply(record) {
+                if self.matches(&object) {
+                    kept.insert(object.id);
+                }
+            }
+        }
+        kept.extend(
+            state
+                .objects
+                .values()
+                .filter(|object| self.matches(object))
+                .map(|object| object.id),
+        );
+        let dropped = |id: u64| all.contains(&id) && !kept.contains(&id);
+
+        let start = self.start.unwrap_or(0.0);
+        let mut filtered = Vec::new();
+        let mut state = WorldState::new();
+        let mut started = self.start.is_none();
+        for record in records {
+            if let Record::Frame(time) = record {
+                if self.end.is_some_and(|end| *time > end) {
+                    break;
+                }
+                if !started && *time >= start {
+                    started = true;
+                    filtered.push(Record::Frame(0.0));
+                    for object in state.objects.values().filter(|o| kept.contains(&o.id)) {
+                        let mut props = Vec::with_capacity(object.props.len() + 1);
+                        if object.coords != Coords::default() {
+                            props.push(Property::T(state.relative_coords(object)));
+                        }
+                        props.extend(
+                            object
+                                .props
+                                .iter()
+                                .filter(|p| !references(p, dropped))
+                                .cloned(),
+                        );
+                        filtered.push(
+                            Update {
+                                id: object.id,
+                                props,
+                            }
+                            .into(),
+                        );
+                    }
+                    if *time == start {
+                        state.apply(record);
+                        continue;
+                    }
+                }
+            }
+            state.apply(record);
+
+            let record = match record {
+                Record::GlobalProperty(GlobalProperty::ReferenceTime(time)) if start != 0.0 => {
+                    let time = DateTime::parse_from_rfc3339(time)?
+                        + Duration::milliseconds((start * 1000.0) as i64);
+                    GlobalProperty::ReferenceTime(time.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+                        .into()
+                }
+                Record::GlobalProperty(_) => record.clone(),
+                _ if !started => continue,
+                Record::Frame(time) => Record::Frame(time - start),
+                Record::Remove(id) if dropped(*id) => continue,
+                Record::Remove(_) => record.clone(),
+                Record::Event(event) => {
+                    if event.object_ids().any(dropped) {
+                        continue;
+                    }
+                    Record::Event(Event::clone(event))
+                }
+                Record::Update(update) => {
+                    if dropped(update.id) {
+                        continue;
+                    }
+                    let props = update
+                        .props
+                        .iter()
+                        .filter(|p| !references(p, dropped))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    if props.is_empty() && !update.props.is_empty() {
+                        continue;
+                    }
+                    Update {
+                        id: update.id,
+                        props,
+                    }
+                    .into()
+                }
+            };
+            filtered.push(record);
+        }
+
+        Ok(filtered)
+    }
+}
+
+/// Whether `p` references an object for which `dropped` is true.
+fn references(p: &Property, dropped: impl Fn(u64) -> bool) -> bool {
+    match p {
+        Property::Parent(id)
+        | Property::Next(id)
+        | Property::FocusedTarget(id)
+        | Property::LockedTarget(id) => dropped(*id),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, record::EventKind};
+
+    #[test]
+    fn test_filter() {
+        let records = parse(
+            "FileType=text/acmi/tacview
+FileVersion=2.2
+0,ReferenceTime=2011-06-02T05:00:00Z
+#0
+a0,T=1|1|1000,Type=Air,Coalition=Blue
+b0,T=2|2|1000,Type=Air,Coalition=Red
+#5
+a0,T=1.5|1|1000,LockedTarget=b0
+#10
+a0,T=2|1|1000
+b0,T=3|2|1000
+0,Event=Destroyed|b0|
+0,Event=Timeout|SourceId:a0|TargetId:b0|
+0,Event=Message|a0|b0|
+#20
+a0,T=3|1|1000
+",
+        )
+        .unwrap();
+
+        let filtered = Filter::new()
+            .time_range(5.0, 12.0)
+            .tag(Tag::Air)
+            .coalition("Blue")
+            .apply(&records)
+            .unwrap();
+        assert_eq!(
+            filtered,
+            vec![
+                GlobalProperty::ReferenceTime("2011-06-02T05:00:05Z".to_string()).into(),
+                Record::Frame(0.0),
+                Update {
+                    id: 0xa0,
+                    props: vec![
+                        Property::T(Coords::default().position(1.0, 1.0, 1000.0)),
+                        Property::Type([Tag::Air].into()),
+                        Property::Coalition("Blue".to_string()),
+                    ]
+                }
+                .into(),
+                Update {
+                    id: 0xa0,
+                    props: vec![Property::T(Coords {
+                        longitude: Some(1.5),
+                        latitude: Some(1.0),
+                        altitude: Some(1000.0),
+                        ..Default::default()
+                    })]
+                }
+                .into(),
+                Record::Frame(5.0),
+                Update {
+                    id: 0xa0,
+                    props: vec![Property::T(Coords::default().position(1.0, 2.0, 1000.0))]
+                }
+                .into(),
+                // text which looks like a dropped id is kept
+                Event {
+                    kind: EventKind::Message,
+                    params: vec!["a0".to_string(), "b0".to_string()],
+                    text: None,
+                }
+                .into(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,684 @@
+//! WGS84 geodesy on [`Coords`].
+//!
+//! Angles are in degrees and distances in meters, as in ACMI. Coordinates must be absolute, i.e.
+//! with `ReferenceLatitude` and `ReferenceLongitude` applied.
+
+use std::{f64::consts::PI, fmt::Display};
+
+use crate::record::Coords;
+
+/// Semi-major axis of the WGS84 ellipsoid, in meters.
+pub const WGS84_A: f64 = 6_378_137.0;
+/// Flattening of the WGS84 ellipsoid.
+pub const WGS84_F: f64 = 1.0 / 298.257_223_563;
+/// Semi-minor axis of the WGS84 ellipsoid, in meters.
+pub const WGS84_B: f64 = WGS84_A * (1.0 - WGS84_F);
+/// First eccentricity squared of the WGS84 ellipsoid.
+const WGS84_E2: f64 = WGS84_F * (2.0 - WGS84_F);
+
+const METERS_PER_NAUTICAL_MILE: f64 = 1852.0;
+const METERS_PER_FOOT: f64 = 0.3048;
+
+/// Earth-centered, earth-fixed cartesian coordinates, in meters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ecef {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+/// Local east, north, up cartesian coordinates relative to an origin, in meters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Enu {
+    pub east: f64,
+    pub north: f64,
+    pub up: f64,
+}
+
+/// Bearing, range and altitude of a target from a bullseye or viewer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bra {
+    /// True bearing, in degrees from 0 to 360.
+    pub bearing: f64,
+    /// Geodesic distance, in meters.
+    pub range: f64,
+    /// Altitude of the target, in meters.
+    pub altitude: f64,
+}
+
+/// [`Bra`] of a target from a viewer, with the aspect of the target.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Braa {
+    pub bra: Bra,
+    /// Aspect angle of the target, see [`Coords::aspect_angle`].
+    pub aspect_angle: f64,
+}
+
+/// Brevity aspect of a target relative to a viewer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aspect {
+    /// Flying towards the viewer, aspect angle of 150° or more.
+    Hot,
+    /// Aspect angle between 110° and 150°.
+    Flank,
+    /// Aspect angle between 70° and 110°.
+    Beam,
+    /// Flying away from the viewer, aspect angle under 70°.
+    Drag,
+}
+
+impl Aspect {
+    pub fn from_angle(aspect_angle: f64) -> Self {
+        match aspect_angle {
+            a if a >= 150.0 => Self::Hot,
+            a if a >= 110.0 => Self::Flank,
+            a if a >= 70.0 => Self::Beam,
+            _ => Self::Drag,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Aspect::Hot => "hot",
+            Aspect::Flank => "flank",
+            Aspect::Beam => "beam",
+            Aspect::Drag => "drag",
+        }
+    }
+}
+
+impl Braa {
+    pub fn aspect(&self) -> Aspect {
+        Aspect::from_angle(self.aspect_angle)
+    }
+}
+
+/// As called, e.g. `045/32, 25000`: bearing in degrees, range in nautical miles and altitude in
+/// feet rounded to hundreds.
+impl Display for Bra {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bearing = self.bearing.round() as u32 % 360;
+        let range = (self.range / METERS_PER_NAUTICAL_MILE).round();
+        let altitude = (self.altitude / METERS_PER_FOOT / 100.0).round() * 100.0;
+        write!(f, "{bearing:03}/{range}, {altitude}")
+    }
+}
+
+/// As called, e.g. `045/32, 25000, hot`.
+impl Display for Braa {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}, {}", self.bra, self.aspect().as_str())
+    }
+}
+
+impl Ecef {
+    /// Geodetic coordinates of the point, with longitude, latitude and altitude set.
+    pub fn to_coords(&self) -> Coords {
+        // Bowring's method, accurate to well under a millimeter for points near the surface
+        let p = self.x.hypot(self.y);
+        let ep2 = (WGS84_A * WGS84_A - WGS84_B * WGS84_B) / (WGS84_B * WGS84_B);
+        let theta = (self.z * WGS84_A).atan2(p * WGS84_B);
+        let latitude = (self.z + ep2 * WGS84_B * theta.sin().powi(3))
+            .atan2(p - WGS84_E2 * WGS84_A * theta.cos().powi(3));
+        let longitude = self.y.atan2(self.x);
+        let altitude = p * latitude.cos() + self.z * latitude.sin()
+            - WGS84_A * (1.0 - WGS84_E2 * latitude.sin().powi(2)).sqrt();
+
+        Coords::default().position(latitude.to_degrees(), longitude.to_degrees(), altitude)
+    }
+
+    pub fn distance(&self, other: &Ecef) -> f64 {
+        ((other.x - self.x).powi(2) + (other.y - self.y).powi(2) + (other.z - self.z).powi(2))
+            .sqrt()
+    }
+}
+
+impl Enu {
+    /// Length of the vector.
+    pub fn norm(&self) -> f64 {
+        (self.east * self.east + self.north * self.north + self.up * self.up).sqrt()
+    }
+
+    /// Angle between two vectors, in degrees from 0 to 180.
+    pub fn angle(&self, other: &Enu) -> f64 {
+        let dot = self.east * other.east + self.north * other.north + self.up * other.up;
+        (dot / (self.norm() * other.norm()))
+            .clamp(-1.0, 1.0)
+            .acos()
+            .to_degrees()
+    }
+}
+
+impl Coords {
+    /// Earth-centered, earth-fixed coordinates, a missing altitude is taken as 0.
+    pub fn to_ecef(&self) -> Option<Ecef> {
+        let latitude = self.latitude?.to_radians();
+        let longitude = self.longitude?.to_radians();
+        let altitude = self.altitude.unwrap_or(0.0);
+        let n = WGS84_A / (1.0 - WGS84_E2 * latitude.sin().powi(2)).sqrt();
+
+        Some(Ecef {
+            x: (n + altitude) * latitude.cos() * longitude.cos(),
+            y: (n + altitude) * latitude.cos() * longitude.sin(),
+            z: (n * (1.0 - WGS84_E2) + altitude) * latitude.sin(),
+        })
+    }
+
+    /// Local east, north, up coordinates relative to `origin`.
+    pub fn to_enu(&self, origin: &Coords) -> Option<Enu> {
+        let point = self.to_ecef()?;
+        let o = origin.to_ecef()?;
+        Some(enu_rotation(origin)?.rotate(Ecef {
+            x: point.x - o.x,
+            y: point.y - o.y,
+            z: point.z - o.z,
+        }))
+    }
+
+    /// Geodetic coordinates of a point given in local east, north, up coordinates relative to
+    /// `origin`.
+    pub fn from_enu(enu: &Enu, origin: &Coords) -> Option<Coords> {
+        let o = origin.to_ecef()?;
+        let d = enu_rotation(origin)?.inverse(enu);
+        Some(
+            Ecef {
+                x: o.x + d.x,
+                y: o.y + d.y,
+                z: o.z + d.z,
+            }
+            .to_coords(),
+        )
+    }
+
+    /// Geodesic distance on the WGS84 ellipsoid to `other`, ignoring altitude.
+    pub fn distance(&self, other: &Coords) -> Option<f64> {
+        Some(self.inverse(other)?.0)
+    }
+
+    /// Initial true bearing of the geodesic to `other`, in degrees from 0 to 360.
+    pub fn bearing(&self, other: &Coords) -> Option<f64> {
+        Some(self.inverse(other)?.1)
+    }
+
+    /// Straight line distance to `other` including altitude.
+    ///
+    /// Objects in a flat world without longitude and latitude use their native `u` and `v`
+    /// coordinates instead.
+    pub fn slant_range(&self, other: &Coords) -> Option<f64> {
+        if let (Some(a), Some(b)) = (self.to_ecef(), other.to_ecef()) {
+            return Some(a.distance(&b));
+        }
+        let ground = (other.u? - self.u?).hypot(other.v? - self.v?);
+        Some(ground.hypot(other.altitude.unwrap_or(0.0) - self.altitude.unwrap_or(0.0)))
+    }
+
+    /// The point at `range` meters along the geodesic with initial `bearing`, keeping the altitude.
+    pub fn destination(&self, bearing: f64, range: f64) -> Option<Coords> {
+        let (latitude, longitude) = vincenty_direct(
+            self.latitude?.to_radians(),
+            self.longitude?.to_radians(),
+            bearing.to_radians(),
+            range,
+        );
+
+        let mut coords = self.clone();
+        coords.latitude = Some(latitude.to_degrees());
+        coords.longitude = Some(normalize_longitude(longitude).to_degrees());
+        Some(coords)
+    }
+
+    /// Bearing, range and altitude of `target` from this point, e.g. a bullseye.
+    pub fn bra(&self, target: &Coords) -> Option<Bra> {
+        let (range, bearing) = self.inverse(target)?;
+        Some(Bra {
+            bearing,
+            range,
+            altitude: target.altitude?,
+        })
+    }
+
+    /// Bearing, range, altitude and aspect of `target` from this object.
+    pub fn braa(&self, target: &Coords) -> Option<Braa> {
+        Some(Braa {
+            bra: self.bra(target)?,
+            aspect_angle: self.aspect_angle(target)?,
+        })
+    }
+
+    /// Angle between the nose of this object and the line of sight to `target`, in degrees from 0
+    /// (target straight ahead) to 180 (target straight behind).
+    pub fn antenna_train_angle(&self, target: &Coords) -> Option<f64> {
+        let los = target.to_enu(self)?;
+        Some(self.nose()?.angle(&los))
+    }
+
+    /// Angle between the tail of `target` and the line of sight from it to this object, in degrees
+    /// from 0 (this object straight behind the target) to 180 (head-on).
+    pub fn aspect_angle(&self, target: &Coords) -> Option<f64> {
+        let los = target.to_enu(self)?;
+        // the line of sight from the target in its own local frame
+        let los = enu_rotation(target)?.rotate(enu_rotation(self)?.inverse(&los));
+        Some(target.nose()?.angle(&los))
+    }
+
+    /// Unit vector of the nose in the local frame, from `yaw` or `heading` and `pitch`.
+    fn nose(&self) -> Option<Enu> {
+        let yaw = self.yaw.or(self.heading)?.to_radians();
+        let pitch = self.pitch.unwrap_or(0.0).to_radians();
+        Some(Enu {
+            east: pitch.cos() * yaw.sin(),
+            north: pitch.cos() * yaw.cos(),
+            up: pitch.sin(),
+        })
+    }
+
+    /// Geodesic distance and initial bearing to `other`.
+    fn inverse(&self, other: &Coords) -> Option<(f64, f64)> {
+        Some(vincenty_inverse(
+            self.latitude?.to_radians(),
+            self.longitude?.to_radians(),
+            other.latitude?.to_radians(),
+            other.longitude?.to_radians(),
+        ))
+    }
+}
+
+/// Rotation from earth-centered to local east, north, up axes at a point.
+struct EnuRotation {
+    sin_lat: f64,
+    cos_lat: f64,
+    sin_lon: f64,
+    cos_lon: f64,
+}
+
+fn enu_rotation(origin: &Coords) -> Option<EnuRotation> {
+    let latitude = origin.latitude?.to_radians();
+    let longitude = origin.longitude?.to_radians();
+    Some(EnuRotation {
+        sin_lat: latitude.sin(),
+        cos_lat: latitude.cos(),
+        sin_lon: longitude.sin(),
+        cos_lon: longitude.cos(),
+    })
+}
+
+impl EnuRotation {
+    fn rotate(&self, d: Ecef) -> Enu {
+        let Self {
+            sin_lat,
+            cos_lat,
+            sin_lon,
+            cos_lon,
+        } = *self;
+        Enu {
+            east: -sin_lon * d.x + cos_lon * d.y,
+            north: -sin_lat * cos_lon * d.x - sin_lat * sin_lon * d.y + cos_lat * d.z,
+            up: cos_lat * cos_lon * d.x + cos_lat * sin_lon * d.y + sin_lat * d.z,
+        }
+    }
+
+    fn inverse(&self, enu: &Enu) -> Ecef {
+        let Self {
+            sin_lat,
+            cos_lat,
+            sin_lon,
+            cos_lon,
+        } = *self;
+        Ecef {
+            x: -sin_lon * enu.east - sin_lat * cos_lon * enu.north + cos_lat * cos_lon * enu.up,
+            y: cos_lon * enu.east - sin_lat * sin_lon * enu.north + cos_lat * sin_lon * enu.up,
+            z: cos_lat * enu.north + sin_lat * enu.up,
+        }
+    }
+}
+
+/// Vincenty's inverse formula, returns the distance and initial bearing in degrees.
+///
+/// Falls back to a spherical approximation for nearly antipodal points, where the iteration does
+/// not converge.
+fn vincenty_inverse(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> (f64, f64) {
+    let l = lon2 - lon1;
+    let u1 = ((1.0 - WGS84_F) * lat1.tan()).atan();
+    let u2 = ((1.0 - WGS84_F) * lat2.tan()).atan();
+    let (sin_u1, cos_u1) = u1.sin_cos();
+    let (sin_u2, cos_u2) = u2.sin_cos();
+
+    let mut lambda = l;
+    for _ in 0..200 {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+        let sin_sigma = ((cos_u2 * sin_lambda).powi(2)
+            + (cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).powi(2))
+        .sqrt();
+        if sin_sigma == 0.0 {
+            // coincident points
+            return (0.0, 0.0);
+        }
+        let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+        let sigma = sin_sigma.atan2(cos_sigma);
+        let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+        let cos2_alpha = 1.0 - sin_alpha * sin_alpha;
+        let cos_2sigma_m = if cos2_alpha == 0.0 {
+            // equatorial line
+            0.0
+        } else {
+            cos_sigma - 2.0 * sin_u1 * sin_u2 / cos2_alpha
+        };
+        let c = WGS84_F / 16.0 * cos2_alpha * (4.0 + WGS84_F * (4.0 - 3.0 * cos2_alpha));
+        let previous = lambda;
+        lambda = l
+            + (1.0 - c)
+                * WGS84_F
+                * sin_alpha
+                * (sigma
+                    + c * sin_sigma
+                        * (cos_2sigma_m + c * cos_sigma * (-1.0 + 2.0 * cos_2sigma_m.powi(2))));
+
+        if (lambda - previous).abs() < 1e-12 {
+            let u_sq = cos2_alpha * (WGS84_A.powi(2) - WGS84_B.powi(2)) / WGS84_B.powi(2);
+            let a =
+                1.0 + u_sq / 16384.0 * (4096.0 + u_sq * (-768.0 + u_sq * (320.0 - 175.0 * u_sq)));
+            let b = u_sq / 1024.0 * (256.0 + u_sq * (-128.0 + u_sq * (74.0 - 47.0 * u_sq)));
+            let delta_sigma = b
+                * sin_sigma
+                * (cos_2sigma_m
+                    + b / 4.0
+                        * (cos_sigma * (-1.0 + 2.0 * cos_2sigma_m.powi(2))
+                            - b / 6.0
+                                * cos_2sigma_m
+                                * (-3.0 + 4.0 * sin_sigma.powi(2))
+                                * (-3.0 + 4.0 * cos_2sigma_m.powi(2))));
+            let distance = WGS84_B * a * (sigma - delta_sigma);
+            let (sin_lambda, cos_lambda) = lambda.sin_cos();
+            let bearing =
+                (cos_u2 * sin_lambda).atan2(cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda);
+            return (distance, normalize_bearing(bearing.to_degrees()));
+        }
+    }
+
+    // spherical approximation on the mean radius
+    let radius = (2.0 * WGS84_A + WGS84_B) / 3.0;
+    let h = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    let bearing = ((lon2 - lon1).sin() * lat2.cos())
+        .atan2(lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * (lon2 - lon1).cos());
+    (
+        2.0 * radius * h.sqrt().min(1.0).asin(),
+        normalize_bearing(bearing.to_degrees()),
+    )
+}
+
+/// Vincenty's direct formula, returns the latitude and longitude of the destination in radians.
+fn vincenty_direct(lat1: f64, lon1: f64, bearing: f64, distance: f64) -> (f64, f64) {
+    let (sin_alpha1, cos_alpha1) = bearing.sin_cos();
+    let tan_u1 = (1.0 - WGS84_F) * lat1.tan();
+    let cos_u1 = 1.0 / (1.0 + tan_u1 * tan_u1).sqrt();
+    let sin_u1 = tan_u1 * cos_u1;
+    let sigma1 = tan_u1.atan2(cos_alpha1);
+    let sin_alpha = cos_u1 * sin_alpha1;
+    let cos2_alpha = 1.0 - sin_alpha * sin_alpha;
+    let u_sq = cos2_alpha * (WGS84_A.powi(2) - WGS84_B.powi(2)) / WGS84_B.powi(2);
+    let a = 1.0 + u_sq / 16384.0 * (4096.0 + u_sq * (-768.0 + u_sq * (320.0 - 175.0 * u_sq)));
+    let b = u_sq / 1024.0 * (256.0 + u_sq * (-128.0 + u_sq * (74.0 - 47.0 * u_sq)));
+
+    let mut sigma = distance / (WGS84_B * a);
+    let mut cos_2sigma_m;
+    let mut sin_sigma;
+    let mut cos_sigma;
+    let mut iterations = 0;
+    loop {
+        cos_2sigma_m = (2.0 * sigma1 + sigma).cos();
+        (sin_sigma, cos_sigma) = sigma.sin_cos();
+        let delta_sigma = b
+            * sin_sigma
+            * (cos_2sigma_m
+                + b / 4.0
+                    * (cos_sigma * (-1.0 + 2.0 * cos_2sigma_m.powi(2))
+                        - b / 6.0
+                            * cos_2sigma_m
+                            * (-3.0 + 4.0 * sin_sigma.powi(2))
+                            * (-3.0 + 4.0 * cos_2sigma_m.powi(2))));
+        let previous = sigma;
+        sigma = distance / (WGS84_B * a) + delta_sigma;
+        iterations += 1;
+        if (sigma - previous).abs() < 1e-12 || iterations >= 200 {
+            break;
+        }
+    }
+    (sin_sigma, cos_sigma) = sigma.sin_cos();
+    cos_2sigma_m = (2.0 * sigma1 + sigma).cos();
+
+    let x = sin_u1 * sin_sigma - cos_u1 * cos_sigma * cos_alpha1;
+    let lat2 = (sin_u1 * cos_sigma + cos_u1 * sin_sigma * cos_alpha1)
+        .atan2((1.0 - WGS84_F) * sin_alpha.hypot(x));
+    let lambda =
+        (sin_sigma * sin_alpha1).atan2(cos_u1 * cos_sigma - sin_u1 * sin_sigma * cos_alpha1);
+    let c = WGS84_F / 16.0 * cos2_alpha * (4.0 + WGS84_F * (4.0 - 3.0 * cos2_alpha));
+    let l = lambda
+        - (1.0 - c)
+            * WGS84_F
+            * sin_alpha
+            * (sigma
+                + c * sin_sigma
+                    * (cos_2sigma_m + c * cos_sigma * (-1.0 + 2.0 * cos_2sigma_m.powi(2))));
+    (lat2, lon1 + l)
+}
+
+fn normalize_bearing(bearing: f64) -> f64 {
+    bearing.rem_euclid(360.0)
+}
+
+fn normalize_longitude(longitude: f64) -> f64 {
+    (longitude + PI).rem_euclid(2.0 * PI) - PI
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dms(degrees: f64, minutes: f64, seconds: f64) -> f64 {
+        degrees.signum() * (degrees.abs() + minutes / 60.0 + seconds / 3600.0)
+    }
+
+    fn assert_close(a: f64, b: f64, tolerance: f64) {
+        assert!((a - b).abs() <= tolerance, "{a} != {b} ± {tolerance}");
+    }
+
+    // Vincenty's worked example, Flinders Peak to Buninyong
+    fn flinders_peak() -> Coords {
+        Coords::default().position(dms(-37.0, 57.0, 3.72030), dms(144.0, 25.0, 29.52440), 0.0)
+    }
+
+    fn buninyong() -> Coords {
+        Coords::default().position(dms(-37.0, 39.0, 10.15610), dms(143.0, 55.0, 35.38390), 0.0)
+    }
+
+    #[test]
+    fn test_distance_bearing() {
+        let (a, b) = (flinders_peak(), buninyong());
+        assert_close(a.distance(&b).unwrap(), 54_972.271, 1e-3);
+        assert_close(a.bearing(&b).unwrap(), dms(306.0, 52.0, 5.37), 1e-5);
+        assert_close(b.bearing(&a).unwrap(), dms(127.0, 10.0, 25.07), 1e-5);
+
+        // one degree of longitude on the equator
+        let origin = Coords::default().position(0.0, 0.0, 0.0);
+        let east = Coords::default().position(0.0, 1.0, 0.0);
+        assert_close(origin.distance(&east).unwrap(), 111_319.491, 1e-3);
+        assert_close(origin.bearing(&east).unwrap(), 90.0, 1e-9);
+
+        assert_eq!(origin.distance(&origin), Some(0.0));
+        // antipodal points fall back to the spherical approximation
+        let antipode = Coords::default().position(0.0, 180.0, 0.0);
+        assert_close(origin.distance(&antipode).unwrap(), 20_015_000.0, 20_000.0);
+
+        assert_eq!(origin.distance(&Coords::default()), None);
+    }
+
+    #[test]
+    fn test_destination() {
+        let destination = flinders_peak()
+            .destination(dms(306.0, 52.0, 5.37), 54_972.271)
+            .unwrap();
+        let expected = buninyong();
+        assert_close(
+            destination.latitude.unwrap(),
+            expected.latitude.unwrap(),
+            1e-8,
+        );
+        assert_close(
+            destination.longitude.unwrap(),
+            expected.longitude.unwrap(),
+            1e-8,
+        );
+        assert_eq!(destination.altitude, Some(0.0));
+
+        // across the antimeridian
+        let destination = Coords::default()
+            .position(0.0, 179.5, 0.0)
+            .destination(90.0, 111_319.491)
+            .unwrap();
+        assert_close(destination.longitude.unwrap(), -179.5, 1e-6);
+    }
+
+    #[test]
+    fn test_slant_range() {
+        let a = Coords::default().position(45.0, 7.0, 1000.0);
+        let b = Coords::default().position(45.0, 7.0, 4000.0);
+        assert_close(a.slant_range(&b).unwrap(), 3000.0, 1e-6);
+
+        // close points on the ellipsoid, the chord is as long as the geodesic
+        let sea_level = Coords::default().position(45.0, 7.0, 0.0);
+        let c = sea_level.destination(30.0, 4000.0).unwrap();
+        assert_close(sea_level.slant_range(&c).unwrap(), 4000.0, 0.01);
+        // above it, the chord is longer by the ratio of the distances to the center of the earth
+        let c = a.destination(30.0, 4000.0).unwrap();
+        assert_close(
+            a.slant_range(&c).unwrap(),
+            4000.0 * (1.0 + 1000.0 / 6.37e6),
+            0.01,
+        );
+
+        let flat = Coords::default().uv(0.0, 0.0);
+        let mut other = Coords::default().uv(3.0, 4.0);
+        other.altitude = Some(12.0);
+        assert_eq!(flat.slant_range(&other), Some(13.0));
+    }
+
+    #[test]
+    fn test_ecef() {
+        let ecef = Coords::default().position(0.0, 0.0, 0.0).to_ecef().unwrap();
+        assert_eq!((ecef.x, ecef.y, ecef.z), (WGS84_A, 0.0, 0.0));
+
+        let pole = Coords::default()
+            .position(90.0, 0.0, 100.0)
+            .to_ecef()
+            .unwrap();
+        assert_close(pole.z, WGS84_B + 100.0, 1e-6);
+
+        for (lat, lon, alt) in [
+            (48.8566, 2.3522, 35.0),
+            (-33.9, 151.2, 10_000.0),
+            (89.9, -120.0, 0.0),
+        ] {
+            let coords = Coords::default()
+                .position(lat, lon, alt)
+                .to_ecef()
+                .unwrap()
+                .to_coords();
+            assert_close(coords.latitude.unwrap(), lat, 1e-9);
+            assert_close(coords.longitude.unwrap(), lon, 1e-9);
+            assert_close(coords.altitude.unwrap(), alt, 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_enu() {
+        let origin = Coords::default().position(36.236, -115.034, 0.0);
+        let north = origin.destination(0.0, 1000.0).unwrap();
+        let enu = north.to_enu(&origin).unwrap();
+        assert_close(enu.east, 0.0, 1e-6);
+        assert_close(enu.north, 1000.0, 0.01);
+        // curvature of the earth
+        assert_close(enu.up, -0.0785, 1e-3);
+
+        let enu = Enu {
+            east: 1200.0,
+            north: -300.0,
+            up: 2500.0,
+        };
+        let back = Coords::from_enu(&enu, &origin)
+            .unwrap()
+            .to_enu(&origin)
+            .unwrap();
+        assert_close(back.east, enu.east, 1e-6);
+        assert_close(back.north, enu.north, 1e-6);
+        assert_close(back.up, enu.up, 1e-6);
+    }
+
+    #[test]
+    fn test_bra() {
+        let bullseye = Coords::default().position(36.0, -115.0, 0.0);
+        let target = bullseye
+            .destination(45.0, 32.0 * METERS_PER_NAUTICAL_MILE)
+            .unwrap();
+        let target = Coords {
+            altitude: Some(25_020.0 * METERS_PER_FOOT),
+            ..target
+        }
+        .orientation(225.0, 0.0, 0.0);
+
+        let bra = bullseye.bra(&target).unwrap();
+        assert_close(bra.bearing, 45.0, 1e-6);
+        assert_close(bra.range, 32.0 * METERS_PER_NAUTICAL_MILE, 1e-3);
+        assert_eq!(bra.to_string(), "045/32, 25000");
+
+        let viewer = bullseye.clone().orientation(45.0, 0.0, 0.0);
+        let braa = viewer.braa(&target).unwrap();
+        assert_eq!(braa.aspect(), Aspect::Hot);
+        assert_eq!(braa.to_string(), "045/32, 25000, hot");
+
+        assert_eq!(target.braa(&bullseye), None);
+        assert_eq!(Aspect::from_angle(90.0), Aspect::Beam);
+        assert_eq!(Aspect::from_angle(30.0), Aspect::Drag);
+    }
+
+    #[test]
+    fn test_aspect_antenna_train_angles() {
+        let own = Coords::default()
+            .position(36.0, -115.0, 5000.0)
+            .orientation(0.0, 0.0, 0.0);
+        let ahead = own.destination(0.0, 10_000.0).unwrap();
+
+        // chasing a target flying away, on its six
+        assert_close(own.antenna_train_angle(&ahead).unwrap(), 0.0, 0.1);
+        assert_close(own.aspect_angle(&ahead).unwrap(), 0.0, 0.1);
+
+        // head-on
+        let head_on = ahead.clone().orientation(180.0, 0.0, 0.0);
+        assert_close(own.aspect_angle(&head_on).unwrap(), 180.0, 0.1);
+
+        // target flying east, beam aspect
+        let beam = ahead.clone().orientation(90.0, 0.0, 0.0);
+        assert_close(own.aspect_angle(&beam).unwrap(), 90.0, 0.1);
+
+        // target off the right wing, behind
+        let right = own.destination(90.0, 10_000.0).unwrap();
+        assert_close(own.antenna_train_angle(&right).unwrap(), 90.0, 0.1);
+        let behind = own.destination(180.0, 10_000.0).unwrap();
+        assert_close(own.antenna_train_angle(&behind).unwrap(), 180.0, 0.1);
+
+        // pitching up towards a target above
+        let above = Coords {
+            altitude: Some(15_000.0),
+            ..ahead.clone()
+        };
+        let climbing = own.clone().orientation(0.0, 45.0, 0.0);
+        assert!(
+            climbing.antenna_train_angle(&above).unwrap()
+                < own.antenna_train_angle(&above).unwrap()
+        );
+
+        // no orientation
+        let unoriented = Coords::default().position(36.0, -115.0, 5000.0);
+        assert_eq!(unoriented.antenna_train_angle(&ahead), None);
+    }
+}
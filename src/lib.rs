@@ -1,32 +1,114 @@
 #![doc = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/README.md"))]
 
+// lets `#[derive(TacviewObject)]` refer to `::bevy_tacview` inside the crate
+#[cfg(feature = "bevy")]
+extern crate self as bevy_tacview;
+
+#[cfg(feature = "bevy")]
 use bevy::prelude::*;
+#[cfg(feature = "octopus")]
 use bevy_octopus::prelude::*;
 
-pub use parser::ParseError;
-pub use systems::TacviewResource;
+#[cfg(feature = "bevy")]
+pub use diagnostics::TacviewDiagnosticsPlugin;
+pub use parser::{parse, ParseError, Parser};
+#[cfg(feature = "bevy")]
+pub use systems::{
+    ErrorPolicy, PeerLagging, PeerQueueLimits, SyncError, TacviewError, TacviewEvent,
+    TacviewResource, TacviewSet,
+};
+#[cfg(feature = "tcp")]
+pub use tcp::TacviewServerPlugin;
 pub use writer::Writer;
 
-use crate::systems::{send_header_after_connected, update_objects, ObjectNeedSync};
+#[cfg(feature = "bevy")]
+use crate::{
+    analysis::TakeoffDetector,
+    components::add_components,
+    record::{
+        Color, Coords, Event, EventKind, GlobalProperty, Property, PropertyKey, PropertyList,
+        Record, Tag, Update,
+    },
+    systems::{
+        collect_events, detect_takeoff_landing, start_sinks, update_bra_labels, update_objects,
+        ObjectNeedSync, PendingEvents,
+    },
+};
 
+pub mod analysis;
+#[cfg(feature = "bevy")]
+pub mod components;
+#[cfg(feature = "bevy")]
+pub mod diagnostics;
+pub mod file;
+pub mod filter;
+pub mod geo;
+pub mod merge;
+#[cfg(feature = "octopus")]
+mod octopus;
 mod parser;
 pub mod record;
+#[cfg(feature = "bevy")]
+pub mod sink;
+pub mod state;
+#[cfg(feature = "bevy")]
 pub mod systems;
+#[cfg(feature = "tcp")]
+pub mod tcp;
+#[cfg(all(test, feature = "bevy"))]
+mod test_utils;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod validate;
 mod writer;
 
+#[cfg(feature = "octopus")]
 pub const TACVIEW_CHANNEL: ChannelId = ChannelId("Tacview client");
 
+#[cfg(feature = "bevy")]
 pub struct TacviewPlugin;
 
+#[cfg(feature = "bevy")]
 impl Plugin for TacviewPlugin {
     fn build(&self, app: &mut App) {
-        if !app.is_plugin_added::<OctopusPlugin>() {
-            app.add_plugins(OctopusPlugin);
-        }
-
         app.init_resource::<TacviewResource>()
+            .init_resource::<PendingEvents>()
+            .init_resource::<ErrorPolicy>()
+            .init_resource::<PeerQueueLimits>()
+            .add_event::<TacviewEvent>()
+            .add_event::<TacviewError>()
+            .add_event::<PeerLagging>()
             .register_type::<ObjectNeedSync>()
-            .add_systems(Update, send_header_after_connected)
-            .add_systems(Update, update_objects);
+            .register_type::<Coords>()
+            .register_type::<Property>()
+            .register_type::<Vec<Property>>()
+            .register_type::<PropertyKey>()
+            .register_type::<PropertyList>()
+            .register_type::<Tag>()
+            .register_type::<Color>()
+            .register_type::<Event>()
+            .register_type::<EventKind>()
+            .register_type::<GlobalProperty>()
+            .register_type::<Update>()
+            .register_type::<Record>()
+            .register_type::<Option<f64>>()
+            .register_type::<Option<String>>()
+            .register_type::<Vec<String>>()
+            .configure_sets(Update, (TacviewSet::Gather, TacviewSet::Sync).chain())
+            .add_systems(
+                Update,
+                (
+                    start_sinks,
+                    detect_takeoff_landing.run_if(resource_exists::<TakeoffDetector>),
+                    update_bra_labels,
+                    collect_events,
+                    update_objects,
+                )
+                    .chain()
+                    .in_set(TacviewSet::Sync),
+            );
+        add_components(app);
+        #[cfg(feature = "octopus")]
+        octopus::add_octopus(app);
     }
 }
@@ -0,0 +1,379 @@
+//! Merge of several recordings into a single timeline.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    record::{GlobalProperty, Property, Record, Update},
+    ParseError,
+};
+
+/// Merge parsed recordings into a single recording.
+///
+/// Recordings are aligned by their `ReferenceTime`, frames of later recordings are offset by the
+/// difference to the earliest one. Object ids already used by a previous recording are remapped
+/// to unused ids, including the ids referenced by `Parent`, `Next`, `FocusedTarget`,
+/// `LockedTarget` and by event parameters. Coordinates are rebased on the `ReferenceLatitude` and
+/// `ReferenceLongitude` of the first recording defining them.
+///
+/// Conflicting global properties are reconciled as follows:
+/// - `ReferenceTime` and `RecordingTime`: the earliest time.
+/// - `Briefing`, `Debriefing` and `Comments`: the distinct values, one per line.
+/// - Any other text property: the distinct values, separated by ` / `.
+/// - Unknown properties: the first value.
+pub fn merge(recordings: &[Vec<Record>]) -> Result<Vec<Record>, ParseError> {
+    let reference_times = recordings
+        .iter()
+        .map(|records| {
+            records
+                .iter()
+                .find_map(|r| match r {
+                    Record::GlobalProperty(GlobalProperty::ReferenceTime(t)) => Some(t),
+                    _ => None,
+                })
+                .map(|t| parse_time(t).map(|time| (time, t)))
+                .transpose()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let base_time = reference_times
+        .iter()
+        .flatten()
+        .min_by_key(|(time, _)| *time);
+
+    let references = recordings
+        .iter()
+        .map(|records| {
+            let (mut latitude, mut longitude) = (None, None);
+            for record in records {
+                match record {
+                    Record::GlobalProperty(GlobalProperty::ReferenceLatitude(v)) => {
+                        latitude = Some(*v)
+                    }
+                    Record::GlobalProperty(GlobalProperty::ReferenceLongitude(v)) => {
+                        longitude = Some(*v)
+                    }
+                    _ => {}
+                }
+            }
+            (latitude, longitude)
+        })
+        .collect::<Vec<_>>();
+    let base_latitude = references.iter().find_map(|r| r.0).unwrap_or(0.0);
+    let base_longitude = references.iter().find_map(|r| r.1).unwrap_or(0.0);
+
+    let mut globals = Globals {
+        reference_latitude: base_latitude,
+        reference_longitude: base_longitude,
+        ..Default::default()
+    };
+    if let Some((_, t)) = base_time {
+        globals.push(GlobalProperty::ReferenceTime(t.to_string()));
+    }
+
+    let mut used_ids = HashSet::<u64>::new();
+    let mut next_id = recordings
+        .iter()
+        .flatten()
+        .filter_map(record_id)
+        .max()
+        .unwrap_or(0);
+    let mut frames = Vec::new();
+
+    for (i, records) in recordings.iter().enumerate() {
+        let offset = match (reference_times[i], base_time) {
+            (Some((time, _)), Some((base, _))) => (time - *base).num_milliseconds() as f64 / 1000.0,
+            _ => 0.0,
+        };
+
+        let (latitude, longitude) = references[i];
+        let latitude = latitude.unwrap_or(0.0) - base_latitude;
+        let longitude = longitude.unwrap_or(0.0) - base_longitude;
+
+        let ids = records.iter().filter_map(record_id).collect::<HashSet<_>>();
+        let mut remap = HashMap::new();
+        for id in &ids {
+            if used_ids.contains(id) {
+                next_id += 1;
+                remap.insert(*id, next_id);
+            }
+        }
+        used_ids.extend(ids.iter().map(|id| remap.get(id).unwrap_or(id)));
+        let map_id = |id: u64| remap.get(&id).copied().unwrap_or(id);
+
+        let mut frame = (offset, i, Vec::new());
+        for record in records {
+            let record = match record {
+                Record::GlobalProperty(p) => {
+                    globals.push(p.clone());
+                    continue;
+                }
+                Record::Frame(time) => {
+                    if !frame.2.is_empty() {
+                        frames.push(frame);
+                    }
+                    frame = (time + offset, i, Vec::new());
+                    continue;
+                }
+                Record::Remove(id) => Record::Remove(map_id(*id)),
+                Record::Event(event) => {
+                    let mut event = event.clone();
+                    event.map_object_ids(map_id);
+                    Record::Event(event)
+                }
+                Record::Update(update) => Record::Update(Update {
+                    id: map_id(update.id),
+                    props: update
+                        .props
+                        .iter()
+                        .map(|p| match p {
+                            Property::T(coords) => {
+                                let mut coords = coords.clone();
+                                coords.latitude = coords.latitude.map(|v| v + latitude);
+                                coords.longitude = coords.longitude.map(|v| v + longitude);
+                                Property::T(coords)
+                            }
+                            Property::Parent(id) => Property::Parent(map_id(*id)),
+                            Property::Next(id) => Property::Next(map_id(*id)),
+                            Property::FocusedTarget(id) => Property::FocusedTarget(map_id(*id)),
+                            Property::LockedTarget(id) => Property::LockedTarget(map_id(*id)),
+                            p => p.clone(),
+                        })
+                        .collect(),
+                }),
+            };
+            frame.2.push(record);
+        }
+        if !frame.2.is_empty() {
+            frames.push(frame);
+        }
+    }
+
+    // stable, so frames at the same time keep the order of the recordings
+    frames.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+    let mut merged = globals.into_records();
+    let mut last_frame = None;
+    for (time, _, records) in frames {
+        if last_frame != Some(time) {
+            merged.push(Record::Frame(time));
+            last_frame = Some(time);
+        }
+        merged.extend(records);
+    }
+
+    Ok(merged)
+}
+
+fn parse_time(time: &str) -> Result<DateTime<Utc>, ParseError> {
+    Ok(DateTime::parse_from_rfc3339(time)?.with_timezone(&Utc))
+}
+
+/// The object id a record is about.
+fn record_id(record: &Record) -> Option<u64> {
+    match record {
+        Record::Update(update) => Some(update.id),
+        Record::Remove(id) => Some(*id),
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct Globals {
+    reference_latitude: f64,
+    reference_longitude: f64,
+    reference_time: Option<String>,
+    recording_time: Option<(DateTime<Utc>, String)>,
+    text: Vec<(&'static str, Vec<String>)>,
+    unknown: Vec<(String, String)>,
+}
+
+impl Globals {
+    fn push(&mut self, p: GlobalProperty) {
+        use GlobalProperty::*;
+        let (name, value) = match p {
+            ReferenceLatitude(_) | ReferenceLongitude(_) => return,
+            ReferenceTime(v) => {
+                self.reference_time.get_or_insert(v);
+                return;
+            }
+            RecordingTime(v) => {
+                if let Ok(time) = parse_time(&v) {
+                    if self.recording_time.as_ref().is_none_or(|(t, _)| time < *t) {
+                        self.recording_time = Some((time, v));
+                    }
+                }
+                return;
+            }
+            Unknown(name, value) => {
+                if !self.unknown.iter().any(|(n, _)| *n == name) {
+                    self.unknown.push((name, value));
+                }
+                return;
+            }
+            DataSource(v) => ("DataSource", v),
+            DataRecorder(v) => ("DataRecorder", v),
+            Author(v) => ("Author", v),
+            Title(v) => ("Title", v),
+            Category(v) => ("Category", v),
+            Briefing(v) => ("Briefing", v),
+            Debriefing(v) => ("Debriefing", v),
+            Comments(v) => ("Comments", v),
+        };
+        if value.is_empty() {
+            return;
+        }
+
+        let index = match self.text.iter().position(|(n, _)| *n == name) {
+            Some(index) => index,
+            None => {
+                self.text.push((name, Vec::new()));
+                self.text.len() - 1
+            }
+        };
+        let values = &mut self.text[index].1;
+        if !values.contains(&value) {
+            values.push(value);
+        }
+    }
+
+    fn into_records(self) -> Vec<Record> {
+        let mut records = Vec::new();
+        if let Some(v) = self.reference_time {
+            records.push(GlobalProperty::ReferenceTime(v).into());
+        }
+        if let Some((_, v)) = self.recording_time {
+            records.push(GlobalProperty::RecordingTime(v).into());
+        }
+        if self.reference_latitude != 0.0 {
+            records.push(GlobalProperty::ReferenceLatitude(self.reference_latitude).into());
+        }
+        if self.reference_longitude != 0.0 {
+            records.push(GlobalProperty::ReferenceLongitude(self.reference_longitude).into());
+        }
+        for (name, values) in self.text {
+            let separator = match name {
+                "Briefing" | "Debriefing" | "Comments" => "\n",
+                _ => " / ",
+            };
+            let value = values.join(separator);
+            let p = match name {
+                "DataSource" => GlobalProperty::DataSource(value),
+                "DataRecorder" => GlobalProperty::DataRecorder(value),
+                "Author" => GlobalProperty::Author(value),
+                "Title" => GlobalProperty::Title(value),
+                "Category" => GlobalProperty::Category(value),
+                "Briefing" => GlobalProperty::Briefing(value),
+                "Debriefing" => GlobalProperty::Debriefing(value),
+                _ => GlobalProperty::Comments(value),
+            };
+            records.push(p.into());
+        }
+        for (name, value) in self.unknown {
+            records.push(GlobalProperty::Unknown(name, value).into());
+        }
+        records
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parser::parse,
+        record::{Coords, Event, EventKind},
+    };
+
+    #[test]
+    fn test_merge() {
+        let a = parse(
+            "FileType=text/acmi/tacview
+FileVersion=2.2
+0,ReferenceTime=2011-06-02T05:00:10Z
+0,ReferenceLatitude=40
+0,Title=Alpha
+#0
+a0,T=1|1|1000,Type=Air
+#5
+0,Event=Destroyed|a0|
+-a0
+",
+        )
+        .unwrap();
+        let b = parse(
+            "FileType=text/acmi/tacview
+FileVersion=2.2
+0,ReferenceTime=2011-06-02T05:00:00Z
+0,ReferenceLatitude=42
+0,Title=Bravo
+#0
+a0,T=2|2|2000,Type=Air
+b0,Type=Weapon,Parent=a0
+#12
+0,Event=Timeout|SourceId:b0|TargetId:a0|
+0,Event=Message|b0|a0|
+-a0
+",
+        )
+        .unwrap();
+
+        let merged = merge(&[a, b]).unwrap();
+        assert_eq!(
+            merged,
+            vec![
+                GlobalProperty::ReferenceTime("2011-06-02T05:00:00Z".to_string()).into(),
+                GlobalProperty::ReferenceLatitude(40.0).into(),
+                GlobalProperty::Title("Alpha / Bravo".to_string()).into(),
+                Record::Frame(0.0),
+                Update {
+                    id: 0xb1,
+                    props: vec![
+                        Property::T(Coords::default().position(4.0, 2.0, 2000.0)),
+                        Property::Type([crate::record::Tag::Air].into()),
+                    ],
+                }
+                .into(),
+                Update {
+                    id: 0xb0,
+                    props: vec![
+                        Property::Type([crate::record::Tag::Weapon].into()),
+                        Property::Parent(0xb1),
+                    ],
+                }
+                .into(),
+                Record::Frame(10.0),
+                Update {
+                    id: 0xa0,
+                    props: vec![
+                        Property::T(Coords::default().position(1.0, 1.0, 1000.0)),
+                        Property::Type([crate::record::Tag::Air].into()),
+                    ],
+                }
+                .into(),
+                Record::Frame(12.0),
+                Event {
+                    kind: EventKind::Timeout,
+                    params: vec!["SourceId:b0".to_string(), "TargetId:b1".to_string()],
+                    text: None,
+                }
+                .into(),
+                // text which looks like a remapped id is left alone
+                Event {
+                    kind: EventKind::Message,
+                    params: vec!["b0".to_string(), "a0".to_string()],
+                    text: None,
+                }
+                .into(),
+                Record::Remove(0xb1),
+                Record::Frame(15.0),
+                Event {
+                    kind: EventKind::Destroyed,
+                    params: vec!["a0".to_string()],
+                    text: None,
+                }
+                .into(),
+                Record::Remove(0xa0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! Real-time telemetry served by `bevy_octopus`, to peers connected on the [`TACVIEW_CHANNEL`].
+
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy_octopus::connections::NetworkPeer;
+use bevy_octopus::prelude::*;
+use bevy_octopus::shared::{NetworkEvent, NetworkNodeEvent};
+use crossbeam_channel::Receiver;
+
+use crate::sink::{QueueSink, Sink, TacviewSink};
+use crate::systems::{report_error, ErrorPolicy, SyncError, TacviewError, REAL_TIME_PROTOCOL};
+use crate::{PeerQueueLimits, TacviewSet, TACVIEW_CHANNEL};
+
+pub(crate) fn add_octopus(app: &mut App) {
+    if !app.is_plugin_added::<OctopusPlugin>() {
+        app.add_plugins(OctopusPlugin);
+    }
+
+    app.add_systems(Update, handle_network_events.before(TacviewSet::Sync))
+        .add_systems(Update, send_octopus_frames.after(TacviewSet::Sync));
+}
+
+/// Sink of a `bevy_octopus` peer, its data is sent by the `NetworkNode` of the entity.
+///
+/// `bevy_octopus` queues whatever it is given without bound, so frames wait in a bounded
+/// [`OctopusOutbox`] and are handed to the `NetworkNode` once it sent the previous ones. The frames
+/// in the outbox and those handed over but not sent yet are queued for the
+/// [`PeerQueueLimits`](crate::PeerQueueLimits).
+struct OctopusSink {
+    sink: QueueSink,
+    /// Frames handed to the `NetworkNode` which it did not send yet.
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl OctopusSink {
+    fn new(capacity: usize) -> (Self, OctopusOutbox) {
+        let (sink, frames) = QueueSink::new(capacity);
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        (
+            Self {
+                sink,
+                in_flight: in_flight.clone(),
+            },
+            OctopusOutbox { frames, in_flight },
+        )
+    }
+}
+
+impl TacviewSink for OctopusSink {
+    fn send(&mut self, data: &[u8]) -> Result<(), io::Error> {
+        self.sink.send(data)
+    }
+
+    fn queued(&self) -> usize {
+        self.sink.queued() + self.in_flight.load(Ordering::Relaxed)
+    }
+
+    fn drop_queued(&mut self) -> usize {
+        self.sink.drop_queued()
+    }
+}
+
+/// Data of an [`OctopusSink`] waiting to be sent by the `NetworkNode`.
+#[derive(Component)]
+struct OctopusOutbox {
+    frames: Receiver<Vec<u8>>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+/// Add a sink to peers connected on the [`TACVIEW_CHANNEL`], starting with the handshake.
+fn handle_network_events(
+    mut network_events: EventReader<NetworkNodeEvent>,
+    q_peer: Query<&NetworkNode, With<NetworkPeer>>,
+    mut commands: Commands,
+    policy: Res<ErrorPolicy>,
+    limits: Res<PeerQueueLimits>,
+    mut errors: EventWriter<TacviewError>,
+) {
+    for event in network_events.read() {
+        if event.channel_id != TACVIEW_CHANNEL {
+            continue;
+        }
+        match &event.event {
+            NetworkEvent::Connected => {
+                info!("Tacview Client Connected {:?}", event.node);
+                if let Ok(net_node) = q_peer.get(event.node) {
+                    // the handshake is not part of the stream, it is never dropped
+                    net_node.send(REAL_TIME_PROTOCOL.as_bytes());
+                    let (sink, outbox) = OctopusSink::new(limits.max_queued_frames);
+                    commands
+                        .entity(event.node)
+                        .insert((Sink::new(sink), outbox));
+                } else {
+                    warn!("Failed to get network node for {:?}", event.node)
+                }
+            }
+            NetworkEvent::Disconnected => {
+                info!("Tacview Client Disconnected");
+            }
+            NetworkEvent::Error(err) => {
+                report_error(
+                    &mut errors,
+                    TacviewError {
+                        peer: Some(event.node),
+                        entity: None,
+                        error: SyncError::Network(format!("{err:?}")),
+                    },
+                );
+                policy.handle_peer_error(event.node, &mut commands);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Pass the data of octopus sinks to the network, once it sent what it was passed before.
+fn send_octopus_frames(q_peers: Query<(&NetworkNode, &OctopusOutbox)>) {
+    for (net_node, outbox) in q_peers.iter() {
+        if !net_node.send_message_channel.sender.is_empty() {
+            continue;
+        }
+        let mut frames = 0;
+        let mut data = Vec::new();
+        for frame in outbox.frames.try_iter() {
+            data.extend_from_slice(&frame);
+            frames += 1;
+        }
+        outbox.in_flight.store(frames, Ordering::Relaxed);
+        if !data.is_empty() {
+            net_node.send(&data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::Property;
+    use crate::systems::{Lagging, ObjectNeedSync, PeerLagging};
+    use crate::test_utils::{spawn_object, test_app};
+
+    #[test]
+    fn test_slow_peer() {
+        let mut app = test_app();
+        app.insert_resource(PeerQueueLimits {
+            max_queued_frames: 2,
+        });
+        let peer = app.world.spawn((NetworkNode::default(), NetworkPeer)).id();
+        app.world.send_event(NetworkNodeEvent {
+            node: peer,
+            channel_id: TACVIEW_CHANNEL,
+            event: NetworkEvent::Connected,
+        });
+        let object = spawn_object(&mut app.world, vec![Property::Health(0.5)]);
+
+        // the node never sends what it was passed
+        let mut lagging = 0;
+        for _ in 0..4 {
+            app.update();
+            app.world.entity_mut(object).insert(ObjectNeedSync::Update);
+            lagging += app
+                .world
+                .resource_mut::<Events<PeerLagging>>()
+                .drain()
+                .count();
+        }
+        assert!(app.world.get::<Lagging>(peer).is_some());
+        assert_eq!(lagging, 1);
+        // the frames wait in the outbox, not in the node
+        let node = app.world.get::<NetworkNode>(peer).unwrap();
+        assert_eq!(node.send_message_channel.sender.len(), 1);
+
+        // once the node sends what it was passed the peer gets a full sync
+        let mut stream = Vec::new();
+        for _ in 0..4 {
+            let node = app.world.get::<NetworkNode>(peer).unwrap();
+            while let Ok(data) = node.send_message_channel.receiver.try_recv() {
+                stream.extend(data);
+            }
+            app.update();
+        }
+        assert!(app.world.get::<Lagging>(peer).is_none());
+        let stream = String::from_utf8(stream).unwrap();
+        let full_sync = format!("{:x},T=||,Health=0.5\n", object.to_bits());
+        assert!(stream.starts_with(&format!("{REAL_TIME_PROTOCOL}FileType=text/acmi/tacview\n")));
+        assert_eq!(stream.matches(&full_sync).count(), 1, "{stream}");
+    }
+}
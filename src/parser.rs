@@ -1,3 +1,5 @@
+use crate::record::{Record, RecordRef};
+
 // TODO: line and position information for certain errors?
 #[derive(Debug, thiserror::Error)]
 pub enum ParseError {
@@ -7,6 +9,8 @@ pub enum ParseError {
     InvalidVersion,
     #[error("error reading input")]
     Io(#[from] std::io::Error),
+    #[error("input is not valid UTF-8")]
+    InvalidUtf8(#[from] std::str::Utf8Error),
     #[error("unexpected end of line")]
     Eol,
     #[error("object id is not a u64")]
@@ -19,6 +23,265 @@ pub enum ParseError {
     InvalidEvent,
     #[error("encountered invalid coordinate format")]
     InvalidCoordinateFormat,
-    // #[error("error reading zip compressed input")]
-    // Zip(#[from] zip::result::ZipError),
+    #[error("invalid date time")]
+    InvalidTime(#[from] chrono::ParseError),
+    #[cfg(feature = "zip")]
+    #[error("error reading zip compressed input")]
+    Zip(#[from] zip::result::ZipError),
+}
+
+/// Parse ACMI text into owned records, failing on the first invalid line.
+pub fn parse(input: &str) -> Result<Vec<Record>, ParseError> {
+    Parser::new(input)?.map(|r| r?.to_record()).collect()
+}
+
+/// Parses ACMI text into [`RecordRef`]s borrowing from the input, e.g. a memory-mapped file.
+///
+/// Lines continued with a trailing backslash are returned as a single record, blank lines and
+/// `//` comments are skipped. A line which fails to parse yields an error and parsing continues
+/// with the next line.
+#[derive(Debug, Clone)]
+pub struct Parser<'a> {
+    rest: &'a str,
+    next_line: usize,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Check the ACMI header and create a parser for the records following it.
+    pub fn new(input: &'a str) -> Result<Self, ParseError> {
+        let mut parser = Self {
+            rest: input.strip_prefix('\u{feff}').unwrap_or(input),
+            next_line: 1,
+            line: 0,
+        };
+
+        if parser.next_line() != Some("FileType=text/acmi/tacview") {
+            return Err(ParseError::InvalidFileType);
+        }
+        match parser.next_line() {
+            Some(version) if version.starts_with("FileVersion=2.") => {}
+            _ => return Err(ParseError::InvalidVersion),
+        }
+
+        Ok(parser)
+    }
+
+    pub fn from_bytes(input: &'a [u8]) -> Result<Self, ParseError> {
+        Self::new(std::str::from_utf8(input)?)
+    }
+
+    /// A parser for records following a header which was already checked, e.g. the rest of a
+    /// stream.
+    pub(crate) fn without_header(input: &'a str) -> Self {
+        Self {
+            rest: input,
+            next_line: 1,
+            line: 0,
+        }
+    }
+
+    /// The line number on which the last returned record started.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The next non-empty line, including its continuation lines, without the line break.
+    fn next_line(&mut self) -> Option<&'a str> {
+        loop {
+            if self.rest.is_empty() {
+                return None;
+            }
+
+            let bytes = self.rest.as_bytes();
+            let mut lines = 1;
+            let mut end = bytes.len();
+            let mut next = bytes.len();
+            for (i, b) in bytes.iter().enumerate() {
+                if *b != b'\n' {
+                    continue;
+                }
+                if is_continued(bytes, i) {
+                    lines += 1;
+                    continue;
+                }
+                end = if i > 0 && bytes[i - 1] == b'\r' { i - 1 } else { i };
+                next = i + 1;
+                break;
+            }
+
+            let line = &self.rest[..end];
+            self.rest = &self.rest[next..];
+            self.line = self.next_line;
+            self.next_line += lines;
+
+            if !line.is_empty() && !line.starts_with("//") {
+                return Some(line);
+            }
+        }
+    }
+
+    /// Parse the remaining records on up to `threads` worker threads, with the line number on
+    /// which each record started.
+    ///
+    /// The input is split into chunks at frame (`#time`) lines which are not part of a continued
+    /// line, and the results are stitched back in order. The returned records, errors and line
+    /// numbers are the same as with the sequential parser. Passing `0` uses the available
+    /// parallelism.
+    pub fn parallel(self, threads: usize) -> Vec<(usize, Result<RecordRef<'a>, ParseError>)> {
+        let threads = match threads {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        let chunks = split_frames(self.rest, threads);
+        if chunks.len() == 1 {
+            return self.collect_lines();
+        }
+
+        std::thread::scope(|s| {
+            let mut next_line = self.next_line;
+            let workers = chunks
+                .into_iter()
+                .map(|rest| {
+                    let parser = Parser {
+                        next_line,
+                        ..Parser::without_header(rest)
+                    };
+                    // chunks end with a line break, continued lines included
+                    next_line += rest.bytes().filter(|&b| b == b'\n').count();
+                    s.spawn(move || parser.collect_lines())
+                })
+                .collect::<Vec<_>>();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|err| std::panic::resume_unwind(err))
+                })
+                .collect()
+        })
+    }
+
+    /// The remaining records with the line number on which they started.
+    fn collect_lines(mut self) -> Vec<(usize, Result<RecordRef<'a>, ParseError>)> {
+        let mut records = Vec::new();
+        while let Some(record) = self.next() {
+            records.push((self.line, record));
+        }
+        records
+    }
+}
+
+impl<'a> Iterator for Parser<'a> {
+    type Item = Result<RecordRef<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_line().map(RecordRef::parse)
+    }
+}
+
+/// Whether the line break at `newline` is escaped, continuing the line on the next one.
+pub(crate) fn is_continued(bytes: &[u8], newline: usize) -> bool {
+    let content = if newline > 0 && bytes[newline - 1] == b'\r' {
+        newline - 1
+    } else {
+        newline
+    };
+    content > 0 && bytes[content - 1] == b'\\'
+}
+
+/// Split `input` into at most `chunks` parts of roughly equal size, each starting with a frame.
+fn split_frames(input: &str, chunks: usize) -> Vec<&str> {
+    let target = input.len() / chunks + 1;
+    let mut parts = Vec::with_capacity(chunks);
+    let mut rest = input;
+    while parts.len() + 1 < chunks && rest.len() > target {
+        let bytes = rest.as_bytes();
+        let Some(at) = (target..bytes.len() - 1)
+            .find(|&i| bytes[i] == b'\n' && bytes[i + 1] == b'#' && !is_continued(bytes, i))
+        else {
+            break;
+        };
+        let (part, r) = rest.split_at(at + 1);
+        parts.push(part);
+        rest = r;
+    }
+    parts.push(rest);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::{GlobalProperty, Record};
+
+    const ACMI: &str = "\u{feff}FileType=text/acmi/tacview\r
+FileVersion=2.2\r
+0,ReferenceTime=2011-06-02T05:00:00Z\r
+0,Comments=Line 1\\\r
+Line 2\r
+// a comment\r
+\r
+#0\r
+a0,T=1|2|3,Name=F-16C\r
+#1.5\r
+-a0\r
+";
+
+    #[test]
+    fn test_parse() {
+        let mut parser = Parser::new(ACMI).unwrap();
+        let mut records = Vec::new();
+        while let Some(record) = parser.next() {
+            records.push((parser.line(), record.unwrap()));
+        }
+
+        assert_eq!(records.len(), 6);
+        assert_eq!(records[1].0, 4);
+        assert_eq!(
+            records[1].1.to_record().unwrap(),
+            Record::GlobalProperty(GlobalProperty::Comments("Line 1\\\r\nLine 2".to_string()))
+        );
+        assert_eq!(records[2], (8, RecordRef::Frame(0.0)));
+        assert_eq!(records[5], (11, RecordRef::Remove(0xa0)));
+    }
+
+    #[test]
+    fn test_parallel() {
+        let mut acmi = String::from("FileType=text/acmi/tacview\nFileVersion=2.2\n");
+        for frame in 0..200 {
+            acmi += &format!("#{frame}\n{:x},T=1|2|{frame},Name=F-16C\n", frame + 1);
+            if frame % 7 == 0 {
+                // continuation lines ending right before a frame must not be split
+                acmi += "0,Comments=First\\\n#not a frame\\\n\n";
+            }
+            if frame % 11 == 0 {
+                acmi += "invalid\n";
+            }
+        }
+
+        let mut parser = Parser::new(&acmi).unwrap();
+        let mut sequential = Vec::new();
+        while let Some(record) = parser.next() {
+            sequential.push((parser.line(), record));
+        }
+        for threads in [1, 2, 3, 8, 64] {
+            let parallel = Parser::new(&acmi).unwrap().parallel(threads);
+            assert_eq!(format!("{parallel:?}"), format!("{sequential:?}"));
+        }
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        assert!(matches!(
+            Parser::new("FileVersion=2.2\n"),
+            Err(ParseError::InvalidFileType)
+        ));
+        assert!(matches!(
+            Parser::new("FileType=text/acmi/tacview\nFileVersion=1.0\n"),
+            Err(ParseError::InvalidVersion)
+        ));
+    }
 }
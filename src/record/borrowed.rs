@@ -0,0 +1,209 @@
+use super::{update::SplitProps, Event, EventKind, GlobalProperty, Property, Record, Update};
+use crate::ParseError;
+
+/// A record borrowed from the input it was parsed from.
+///
+/// Parsing a `RecordRef` only splits the line, string values are never copied and properties are
+/// only parsed when iterating [`UpdateRef::props`]. Use [`RecordRef::to_record`] to get the owned
+/// [`Record`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordRef<'a> {
+    GlobalProperty(PropertyRef<'a>),
+    Event(EventRef<'a>),
+    Remove(u64),
+    Frame(f64),
+    Update(UpdateRef<'a>),
+}
+
+/// A `Name=Value` pair of an object or global property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PropertyRef<'a> {
+    pub name: &'a str,
+    /// The raw value, escaped commas and line breaks are kept as is.
+    pub value: &'a str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpdateRef<'a> {
+    pub id: u64,
+    props: &'a str,
+}
+
+/// The `Kind|Param|...|Text` body of an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventRef<'a> {
+    raw: &'a str,
+}
+
+/// Iterator over the properties of an [`UpdateRef`].
+#[derive(Debug, Clone)]
+pub struct PropsRef<'a> {
+    inner: SplitProps<'a>,
+}
+
+impl<'a> RecordRef<'a> {
+    /// Parse a single (possibly continued) line, without its line break.
+    pub fn parse(line: &'a str) -> Result<Self, ParseError> {
+        if let Some(time) = line.strip_prefix('#') {
+            return Ok(Self::Frame(time.parse()?));
+        }
+        if let Some(id) = line.strip_prefix('-') {
+            return Ok(Self::Remove(u64::from_str_radix(id, 16)?));
+        }
+
+        let (id, rest) = line.split_once(',').ok_or(ParseError::Eol)?;
+        let id = u64::from_str_radix(id, 16)?;
+        if id == 0 {
+            if let Some(event) = rest.strip_prefix("Event=") {
+                return Ok(Self::Event(EventRef::new(event)));
+            }
+            return Ok(Self::GlobalProperty(PropertyRef::parse(rest)?));
+        }
+
+        Ok(Self::Update(UpdateRef { id, props: rest }))
+    }
+
+    /// Convert into an owned [`Record`], parsing all property values.
+    pub fn to_record(&self) -> Result<Record, ParseError> {
+        Ok(match self {
+            Self::GlobalProperty(p) => Record::GlobalProperty(p.to_global_property()?),
+            Self::Event(e) => Record::Event(e.to_event()),
+            Self::Remove(id) => Record::Remove(*id),
+            Self::Frame(time) => Record::Frame(*time),
+            Self::Update(u) => Record::Update(u.to_update()?),
+        })
+    }
+}
+
+impl<'a> TryFrom<RecordRef<'a>> for Record {
+    type Error = ParseError;
+
+    fn try_from(record: RecordRef<'a>) -> Result<Self, Self::Error> {
+        record.to_record()
+    }
+}
+
+impl<'a> PropertyRef<'a> {
+    pub fn parse(s: &'a str) -> Result<Self, ParseError> {
+        let (name, value) = s.split_once('=').ok_or(ParseError::MissingDelimiter('='))?;
+        Ok(Self { name, value })
+    }
+
+    pub fn to_property(&self) -> Result<Property, ParseError> {
+        Property::from_parts(self.name, self.value)
+    }
+
+    pub fn to_global_property(&self) -> Result<GlobalProperty, ParseError> {
+        GlobalProperty::from_parts(self.name, self.value)
+    }
+}
+
+impl<'a> UpdateRef<'a> {
+    pub fn props(&self) -> PropsRef<'a> {
+        PropsRef {
+            inner: SplitProps::new(self.props),
+        }
+    }
+
+    pub fn to_update(&self) -> Result<Update, ParseError> {
+        Ok(Update {
+            id: self.id,
+            props: self
+                .props()
+                .map(|p| p.and_then(|p| p.to_property()))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl<'a> Iterator for PropsRef<'a> {
+    type Item = Result<PropertyRef<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(PropertyRef::parse)
+    }
+}
+
+impl<'a> EventRef<'a> {
+    pub fn new(raw: &'a str) -> Self {
+        Self { raw }
+    }
+
+    pub fn kind(&self) -> &'a str {
+        self.split().0
+    }
+
+    pub fn params(&self) -> impl Iterator<Item = &'a str> {
+        self.split().1.into_iter().flat_map(|p| p.split('|'))
+    }
+
+    /// The trailing text of the event, `None` if empty.
+    pub fn text(&self) -> Option<&'a str> {
+        self.split().2.filter(|s| !s.is_empty())
+    }
+
+    pub fn to_event(&self) -> Event {
+        Event {
+            kind: EventKind::from(self.kind()),
+            params: self.params().map(String::from).collect(),
+            text: self.text().map(String::from),
+        }
+    }
+
+    fn split(&self) -> (&'a str, Option<&'a str>, Option<&'a str>) {
+        match self.raw.split_once('|') {
+            None => (self.raw, None, None),
+            Some((kind, rest)) => match rest.rsplit_once('|') {
+                None => (kind, None, Some(rest)),
+                Some((params, text)) => (kind, Some(params), Some(text)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_borrowed_matches_owned() {
+        let cases = [
+            ("#12.5", Record::Frame(12.5)),
+            ("-a0", Record::Remove(0xa0)),
+            (
+                "0,Title=Test\\, mission",
+                GlobalProperty::from_str("Title=Test\\, mission").unwrap().into(),
+            ),
+            (
+                "0,Event=Destroyed|a0|",
+                Event::from_str("Destroyed|a0|").unwrap().into(),
+            ),
+            (
+                "0,Event=Message|a0|b1|Hello",
+                Event::from_str("Message|a0|b1|Hello").unwrap().into(),
+            ),
+            ("0,Event=Bookmark", Event::from_str("Bookmark").unwrap().into()),
+            (
+                "a0,T=1|2|3,Name=F-16C\\,52,Type=Air+FixedWing",
+                Update::from_str("a0,T=1|2|3,Name=F-16C\\,52,Type=Air+FixedWing")
+                    .unwrap()
+                    .into(),
+            ),
+            ("a0,", Update::from_str("a0,").unwrap().into()),
+        ];
+
+        for (line, expected) in cases {
+            assert_eq!(RecordRef::parse(line).unwrap().to_record().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_event_parts() {
+        let event = EventRef::new("TakenOff|a0|b1|Took off");
+        assert_eq!(event.kind(), "TakenOff");
+        assert_eq!(event.params().collect::<Vec<_>>(), ["a0", "b1"]);
+        assert_eq!(event.text(), Some("Took off"));
+    }
+}
@@ -1,8 +1,12 @@
 use std::{fmt::Display, str::FromStr};
 
+#[cfg(feature = "bevy")]
+use bevy::reflect::Reflect;
+
 use crate::ParseError;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "bevy", derive(Reflect))]
 pub struct Event {
     pub kind: EventKind,
     pub params: Vec<String>,
@@ -10,6 +14,7 @@ pub struct Event {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "bevy", derive(Reflect))]
 pub enum EventKind {
     /// Generic event.
     Message,
@@ -60,17 +65,7 @@ impl FromStr for Event {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut parts = s.split('|');
         let kind = parts.next().ok_or(ParseError::InvalidEvent)?;
-        let kind = match kind {
-            "Message" => EventKind::Message,
-            "Bookmark" => EventKind::Bookmark,
-            "Debug" => EventKind::Debug,
-            "LeftArea" => EventKind::LeftArea,
-            "Destroyed" => EventKind::Destroyed,
-            "TakenOff" => EventKind::TakenOff,
-            "Landed" => EventKind::Landed,
-            "Timeout" => EventKind::Timeout,
-            name => EventKind::Unknown(name.to_string()),
-        };
+        let kind = EventKind::from(kind);
 
         let mut params = parts.map(String::from).collect::<Vec<_>>();
         let text = if params.is_empty() {
@@ -83,6 +78,44 @@ impl FromStr for Event {
     }
 }
 
+impl Event {
+    /// Ids of the objects the event is about, only at the positions its kind defines.
+    pub fn object_ids(&self) -> impl Iterator<Item = u64> + '_ {
+        self.params
+            .iter()
+            .enumerate()
+            .filter_map(|(i, param)| object_id_param(&self.kind, i, param))
+            .map(|(_, id)| id)
+    }
+
+    /// Replace the ids of the objects the event is about, keeping the other params.
+    pub fn map_object_ids(&mut self, mut f: impl FnMut(u64) -> u64) {
+        for (i, param) in self.params.iter_mut().enumerate() {
+            if let Some((key, id)) = object_id_param(&self.kind, i, param) {
+                *param = format!("{key}{:x}", f(id));
+            }
+        }
+    }
+}
+
+/// The object id in the param at `index` of an event of `kind`, with the `Key:` before it.
+fn object_id_param<'a>(kind: &EventKind, index: usize, param: &'a str) -> Option<(&'a str, u64)> {
+    use EventKind::*;
+    let (key, id) = match kind {
+        // the other params are parts of the text
+        Message | Bookmark | Debug | LeftArea | Destroyed | TakenOff | Landed if index == 0 => {
+            ("", param)
+        }
+        // `Key:Value` params, only the source and the target are objects
+        Timeout => match param.split_once(':') {
+            Some((key @ ("SourceId" | "TargetId"), id)) => (&param[..=key.len()], id),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    u64::from_str_radix(id, 16).ok().map(|id| (key, id))
+}
+
 impl Display for Event {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "0,Event={}", self.kind.as_str())?;
@@ -94,8 +127,24 @@ impl Display for Event {
     }
 }
 
+impl From<&str> for EventKind {
+    fn from(s: &str) -> Self {
+        match s {
+            "Message" => Self::Message,
+            "Bookmark" => Self::Bookmark,
+            "Debug" => Self::Debug,
+            "LeftArea" => Self::LeftArea,
+            "Destroyed" => Self::Destroyed,
+            "TakenOff" => Self::TakenOff,
+            "Landed" => Self::Landed,
+            "Timeout" => Self::Timeout,
+            name => Self::Unknown(name.to_string()),
+        }
+    }
+}
+
 impl EventKind {
-    fn as_str(&self) -> &str {
+    pub fn as_str(&self) -> &str {
         use EventKind::*;
         match self {
             Message => "Message",
@@ -115,6 +164,26 @@ impl EventKind {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_object_ids() {
+        let mut event = Event::from_str("Timeout|SourceId:a1|AmmoType:FOX2|TargetId:c0|").unwrap();
+        assert_eq!(event.object_ids().collect::<Vec<_>>(), [0xa1, 0xc0]);
+        event.map_object_ids(|id| id + 1);
+        assert_eq!(
+            event.params,
+            ["SourceId:a2", "AmmoType:FOX2", "TargetId:c1"]
+        );
+
+        // text which looks like an id is left alone
+        let mut event = Event::from_str("Message|a1|beef|Hello").unwrap();
+        assert_eq!(event.object_ids().collect::<Vec<_>>(), [0xa1]);
+        event.map_object_ids(|id| id + 1);
+        assert_eq!(event.params, ["a2", "beef"]);
+
+        let event = Event::from_str("Custom|a1|").unwrap();
+        assert_eq!(event.object_ids().count(), 0);
+    }
+
     #[test]
     fn test_empty_event_text() {
         assert_eq!(
@@ -1,8 +1,12 @@
 use std::{fmt::Display, str::FromStr};
 
+#[cfg(feature = "bevy")]
+use bevy::reflect::Reflect;
+
 use crate::{record::Precision, ParseError};
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "bevy", derive(Reflect))]
 pub enum GlobalProperty {
     /// Source simulator, control station or file format.
     DataSource(String),
@@ -55,7 +59,13 @@ impl FromStr for GlobalProperty {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (name, value) = s.split_once('=').ok_or(ParseError::MissingDelimiter('='))?;
+        Self::from_parts(name, value)
+    }
+}
 
+impl GlobalProperty {
+    /// Parse a global property from its already split name and value.
+    pub(crate) fn from_parts(name: &str, value: &str) -> Result<Self, ParseError> {
         Ok(match name {
             "DataSource" => Self::DataSource(value.to_string()),
             "DataRecorder" => Self::DataRecorder(value.to_string()),
@@ -87,14 +97,22 @@ impl Display for GlobalProperty {
             Category(v) => write!(f, "0,Category={v}"),
             Briefing(v) => write!(f, "0,Briefing={v}"),
             Debriefing(v) => write!(f, "0,Debriefing={v}"),
-            Comments(v) => write!(
-                f,
-                "0,Comments={}",
-                v.replace("\r\n", "\\\r\n").replace('\n', "\\\n")
-            ),
+            Comments(v) => {
+                f.write_str("0,Comments=")?;
+                let mut rest = v.as_str();
+                while let Some(i) = rest.find('\n') {
+                    let (line, cr) = match rest[..i].strip_suffix('\r') {
+                        Some(line) => (line, "\r"),
+                        None => (&rest[..i], ""),
+                    };
+                    writeln!(f, "{line}\\{cr}")?;
+                    rest = &rest[i + 1..];
+                }
+                f.write_str(rest)
+            }
             ReferenceLongitude(v) => write!(f, "0,ReferenceLongitude={}", v.max_precision(7)),
             ReferenceLatitude(v) => write!(f, "0,ReferenceLatitude={}", v.max_precision(7)),
-            Unknown(v, _) => write!(f, "0,Unknown={v}"),
+            Unknown(k, v) => write!(f, "0,{k}={v}"),
         }
     }
 }
@@ -1,19 +1,30 @@
+mod borrowed;
 mod event;
 mod global_property;
 mod property;
+mod property_list;
 mod update;
 
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
+#[cfg(feature = "bevy")]
+use bevy::reflect::Reflect;
+
+pub use borrowed::{EventRef, PropertyRef, PropsRef, RecordRef, UpdateRef};
 pub use event::{Event, EventKind};
 pub use global_property::GlobalProperty;
-pub use property::{Color, Coords, Property, PropertyList, Tag};
+pub use property::{Color, Coords, Property, Tag};
+pub use property_list::{PropertyKey, PropertyList};
 pub use update::Update;
 
+use crate::ParseError;
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "bevy", derive(Reflect))]
 pub enum Record {
     GlobalProperty(GlobalProperty),
     Event(Event),
+    /// Removal of an object. Object ids are written in hexadecimal, as they are parsed.
     Remove(u64),
     Frame(f64),
     Update(Update),
@@ -24,13 +35,21 @@ impl Display for Record {
         match self {
             Record::GlobalProperty(r) => r.fmt(f),
             Record::Event(r) => r.fmt(f),
-            Record::Remove(id) => write!(f, "-{id}"),
+            Record::Remove(id) => write!(f, "-{id:x}"),
             Record::Frame(n) => write!(f, "#{}", n.max_precision(2)),
             Record::Update(r) => r.fmt(f),
         }
     }
 }
 
+impl FromStr for Record {
+    type Err = ParseError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        RecordRef::parse(line)?.to_record()
+    }
+}
+
 impl From<GlobalProperty> for Record {
     fn from(p: GlobalProperty) -> Self {
         Self::GlobalProperty(p)
@@ -49,7 +68,7 @@ impl From<Update> for Record {
     }
 }
 
-trait Precision {
+pub(crate) trait Precision {
     fn max_precision(self, max_precision: u32) -> Self;
 }
 
@@ -68,7 +87,9 @@ impl Precision for Option<f64> {
 
 #[cfg(test)]
 mod test {
-    use super::Precision;
+    use std::str::FromStr;
+
+    use super::{GlobalProperty, Precision, Property, Record, Update};
 
     #[test]
     #[allow(clippy::float_cmp)]
@@ -79,4 +100,74 @@ mod test {
         assert_eq!(12.3456789.max_precision(3), 12.346);
         assert_eq!(12.3.max_precision(6), 12.3);
     }
+
+    #[test]
+    fn test_write_read() {
+        // ids were written in decimal and unknown globals as `Unknown`, which read back as
+        // another object and another property
+        for (record, line) in [
+            (Record::Remove(0xa0), "-a0"),
+            (
+                Update {
+                    id: 0xa0,
+                    props: vec![Property::Parent(0xb1)],
+                }
+                .into(),
+                "a0,Parent=b1",
+            ),
+            (
+                GlobalProperty::Unknown("Custom".to_string(), "a".to_string()).into(),
+                "0,Custom=a",
+            ),
+        ] {
+            assert_eq!(record.to_string(), line);
+            assert_eq!(Record::from_str(line).unwrap(), record);
+        }
+    }
+
+    #[cfg(feature = "bevy")]
+    #[test]
+    fn test_reflect_serde() {
+        use bevy::prelude::{AppTypeRegistry, FromReflect};
+        use bevy::reflect::serde::{ReflectSerializer, UntypedReflectDeserializer};
+        use serde::de::DeserializeSeed;
+
+        use super::*;
+        use crate::test_utils::test_app;
+
+        fn roundtrip<T: FromReflect>(value: &T, registry: &bevy::reflect::TypeRegistry) -> T {
+            let ron = ron::to_string(&ReflectSerializer::new(value, registry)).unwrap();
+            let mut de = ron::Deserializer::from_str(&ron).unwrap();
+            let reflected = UntypedReflectDeserializer::new(registry)
+                .deserialize(&mut de)
+                .unwrap();
+            T::from_reflect(&*reflected).unwrap()
+        }
+
+        let app = test_app();
+        let registry = app.world.resource::<AppTypeRegistry>().read();
+
+        let props = vec![
+            Property::T(Coords::default().position(1.0, 2.0, 3.0)),
+            Property::Type([Tag::Air, Tag::FixedWing].into()),
+            Property::FuelWeight(2, 1000.0),
+            Property::Unknown("Custom".to_string(), "a".to_string()),
+        ];
+        let list = PropertyList::from(props.clone());
+        assert_eq!(roundtrip(&list, &registry), list);
+
+        for record in [
+            Record::Update(Update { id: 0x102, props }),
+            Record::Event(Event {
+                kind: EventKind::Bookmark,
+                params: vec!["102".to_string()],
+                text: Some("text".to_string()),
+            }),
+            Record::GlobalProperty(GlobalProperty::ReferenceLatitude(45.0)),
+            Record::Remove(0x102),
+            Record::Frame(1.5),
+        ] {
+            assert_eq!(roundtrip(&record, &registry), record);
+        }
+    }
 }
@@ -1,14 +1,22 @@
 #![allow(clippy::upper_case_acronyms)]
 
-use bevy::prelude::Component;
-use std::{borrow::Cow, collections::HashSet, fmt::Display, str::FromStr};
+#[cfg(feature = "bevy")]
+use bevy::prelude::{Component, ReflectComponent};
+#[cfg(feature = "bevy")]
+use bevy::reflect::{Reflect, ReflectDeserialize, ReflectSerialize};
+#[cfg(feature = "bevy")]
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fmt::Display, str::FromStr};
 
 use crate::{record::Precision, ParseError};
 
-#[derive(Debug, PartialEq, Component)]
-pub struct PropertyList(pub Vec<Property>);
-
+/// A property of an object, reflected as a value since the tags of `Type` are a `HashSet`.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "bevy",
+    derive(Reflect, Serialize, Deserialize),
+    reflect_value(Debug, PartialEq, Serialize, Deserialize)
+)]
 pub enum Property {
     /// Object Coordinates.
     T(Coords),
@@ -374,7 +382,12 @@ pub enum Property {
     Unknown(String, String),
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Component)]
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "bevy",
+    derive(Component, Reflect, Serialize, Deserialize),
+    reflect(Component)
+)]
 pub struct Coords {
     /// Unit: deg
     pub longitude: Option<f64>,
@@ -461,7 +474,13 @@ impl Coords {
     }
 }
 
+/// Color of an object, also a component gathered into the `Color` property of its entity.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "bevy",
+    derive(Component, Reflect, Serialize, Deserialize),
+    reflect(Component)
+)]
 pub enum Color {
     Red,
     Orange,
@@ -473,6 +492,7 @@ pub enum Color {
 }
 
 #[derive(Debug, Hash, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "bevy", derive(Reflect, Serialize, Deserialize))]
 pub enum Tag {
     // Class
     Air,
@@ -530,7 +550,86 @@ impl FromStr for Property {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (name, value) = s.split_once('=').ok_or(ParseError::MissingDelimiter('='))?;
+        Self::from_parts(name, value)
+    }
+}
 
+impl Property {
+    /// The value of properties with a numeric (`f64`) value.
+    pub fn as_f64(&self) -> Option<f64> {
+        use Property::*;
+        match self {
+            Importance(v)
+            | Health(v)
+            | Length(v)
+            | Width(v)
+            | Height(v)
+            | Radius(v)
+            | IAS(v)
+            | CAS(v)
+            | TAS(v)
+            | Mach(v)
+            | AOA(v)
+            | AOS(v)
+            | AGL(v)
+            | HDG(v)
+            | HDM(v)
+            | Throttle(v)
+            | Afterburner(v)
+            | AirBrakes(v)
+            | Flaps(v)
+            | LandingGear(v)
+            | LandingGearHandle(v)
+            | Tailhook(v)
+            | Parachute(v)
+            | DragChute(v)
+            | RadarMode(v)
+            | RadarAzimuth(v)
+            | RadarElevation(v)
+            | RadarRoll(v)
+            | RadarRange(v)
+            | RadarHorizontalBeamwidth(v)
+            | RadarVerticalBeamwidth(v)
+            | LockedTargetMode(v)
+            | LockedTargetAzimuth(v)
+            | LockedTargetElevation(v)
+            | LockedTargetRange(v)
+            | EngagementMode(v)
+            | EngagementMode2(v)
+            | EngagementRange(v)
+            | EngagementRange2(v)
+            | VerticalEngagementRange(v)
+            | VerticalEngagementRange2(v)
+            | RollControlInput(v)
+            | PitchControlInput(v)
+            | YawControlInput(v)
+            | RollControlPosition(v)
+            | PitchControlPosition(v)
+            | YawControlPosition(v)
+            | RollTrimTab(v)
+            | PitchTrimTab(v)
+            | YawTrimTab(v)
+            | AileronLeft(v)
+            | AileronRight(v)
+            | Elevator(v)
+            | Rudder(v)
+            | PilotHeadRoll(v)
+            | PilotHeadPitch(v)
+            | PilotHeadYaw(v)
+            | VerticalGForce(v)
+            | LongitudinalGForce(v)
+            | LateralGForce(v)
+            | ENL(v)
+            | FuelWeight(_, v)
+            | FuelVolume(_, v)
+            | FuelFlowWeight(_, v)
+            | FuelFlowVolume(_, v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Parse a property from its already split name and value.
+    pub(crate) fn from_parts(name: &str, value: &str) -> Result<Self, ParseError> {
         Ok(match name {
             "T" => Property::T(Coords::from_str(value)?),
             "Name" => Property::Name(value.to_string()),
@@ -587,16 +686,16 @@ impl FromStr for Property {
             "FuelWeight7" => Property::FuelWeight(6, FromStr::from_str(value)?),
             "FuelWeight8" => Property::FuelWeight(7, FromStr::from_str(value)?),
             "FuelWeight9" => Property::FuelWeight(8, FromStr::from_str(value)?),
-            "FuelVolume" => Property::FuelVolume(0, FromStr::from_str(value)?),
-            "FuelVolume1" => Property::FuelVolume(1, FromStr::from_str(value)?),
-            "FuelVolume2" => Property::FuelVolume(2, FromStr::from_str(value)?),
-            "FuelVolume3" => Property::FuelVolume(3, FromStr::from_str(value)?),
-            "FuelVolume4" => Property::FuelVolume(4, FromStr::from_str(value)?),
-            "FuelVolume5" => Property::FuelVolume(5, FromStr::from_str(value)?),
-            "FuelVolume6" => Property::FuelVolume(6, FromStr::from_str(value)?),
-            "FuelVolume7" => Property::FuelVolume(7, FromStr::from_str(value)?),
-            "FuelVolume8" => Property::FuelVolume(8, FromStr::from_str(value)?),
-            "FuelVolume9" => Property::FuelVolume(9, FromStr::from_str(value)?),
+            // the first tank has no index, `FuelVolume1` was accepted for it before
+            "FuelVolume" | "FuelVolume1" => Property::FuelVolume(0, FromStr::from_str(value)?),
+            "FuelVolume2" => Property::FuelVolume(1, FromStr::from_str(value)?),
+            "FuelVolume3" => Property::FuelVolume(2, FromStr::from_str(value)?),
+            "FuelVolume4" => Property::FuelVolume(3, FromStr::from_str(value)?),
+            "FuelVolume5" => Property::FuelVolume(4, FromStr::from_str(value)?),
+            "FuelVolume6" => Property::FuelVolume(5, FromStr::from_str(value)?),
+            "FuelVolume7" => Property::FuelVolume(6, FromStr::from_str(value)?),
+            "FuelVolume8" => Property::FuelVolume(7, FromStr::from_str(value)?),
+            "FuelVolume9" => Property::FuelVolume(8, FromStr::from_str(value)?),
             "FuelFlowWeight" => Property::FuelFlowWeight(0, FromStr::from_str(value)?),
             "FuelFlowWeight2" => Property::FuelFlowWeight(1, FromStr::from_str(value)?),
             "FuelFlowWeight3" => Property::FuelFlowWeight(2, FromStr::from_str(value)?),
@@ -667,7 +766,16 @@ impl Display for Property {
         match self {
             T(v) => write!(f, "T={v}"),
             Name(v) => write!(f, "Name={v}"),
-            Type(v) => write!(f, "Type={}", join(v.iter().map(|v| v.as_str()), "+")),
+            Type(v) => {
+                f.write_str("Type=")?;
+                for (i, tag) in v.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("+")?;
+                    }
+                    f.write_str(tag.as_str())?;
+                }
+                Ok(())
+            }
             Parent(v) => write!(f, "Parent={v:x}"),
             Next(v) => write!(f, "Next={v:x}"),
             CallSign(v) => write!(f, "CallSign={v}"),
@@ -711,10 +819,10 @@ impl Display for Property {
             Tailhook(v) => write!(f, "Tailhook={v}"),
             Parachute(v) => write!(f, "Parachute={v}"),
             DragChute(v) => write!(f, "DragChute={v}"),
-            FuelWeight(i, v) => write!(f, "FuelWeight{}={}", to_index(*i), v),
-            FuelVolume(i, v) => write!(f, "FuelVolume{}={}", to_index(*i), v),
-            FuelFlowWeight(i, v) => write!(f, "FuelFlowWeight{}={}", to_index(*i), v),
-            FuelFlowVolume(i, v) => write!(f, "FuelFlowVolume{}={}", to_index(*i), v),
+            FuelWeight(i, v) => write!(f, "FuelWeight{}={v}", Index(*i)),
+            FuelVolume(i, v) => write!(f, "FuelVolume{}={v}", Index(*i)),
+            FuelFlowWeight(i, v) => write!(f, "FuelFlowWeight{}={v}", Index(*i)),
+            FuelFlowVolume(i, v) => write!(f, "FuelFlowVolume{}={v}", Index(*i)),
             RadarMode(v) => write!(f, "RadarMode={v}"),
             RadarAzimuth(v) => write!(f, "RadarAzimuth={v}"),
             RadarElevation(v) => write!(f, "RadarElevation={v}"),
@@ -757,7 +865,7 @@ impl Display for Property {
     }
 }
 
-impl<'a> From<&'a str> for Color {
+impl From<&str> for Color {
     fn from(s: &str) -> Self {
         match s {
             "Red" => Self::Red,
@@ -771,7 +879,7 @@ impl<'a> From<&'a str> for Color {
 }
 
 impl Color {
-    fn as_str(&self) -> &str {
+    pub fn as_str(&self) -> &str {
         use Color::*;
         match self {
             Red => "Red",
@@ -785,7 +893,7 @@ impl Color {
     }
 }
 
-impl<'a> From<&'a str> for Tag {
+impl From<&str> for Tag {
     fn from(s: &str) -> Self {
         match s {
             "Air" => Self::Air,
@@ -838,7 +946,7 @@ impl<'a> From<&'a str> for Tag {
 }
 
 impl Tag {
-    fn as_str(&self) -> &str {
+    pub fn as_str(&self) -> &str {
         use Tag::*;
         match self {
             Air => "Air",
@@ -1031,15 +1139,6 @@ impl Display for Coords {
     }
 }
 
-fn join<'a>(iter: impl Iterator<Item = &'a str>, sep: &'a str) -> String {
-    iter.fold(String::new(), |mut acc, v| {
-        if !acc.is_empty() {
-            acc += sep;
-        }
-        acc + v
-    })
-}
-
 struct NoneAsEmpty<V>(Option<V>);
 
 impl<V: Display> Display for NoneAsEmpty<V> {
@@ -1052,17 +1151,34 @@ impl<V: Display> Display for NoneAsEmpty<V> {
     }
 }
 
-fn to_index(i: u8) -> Cow<'static, str> {
-    match i {
-        0 => Cow::Borrowed(""),
-        1 => Cow::Borrowed("2"),
-        2 => Cow::Borrowed("3"),
-        3 => Cow::Borrowed("4"),
-        4 => Cow::Borrowed("5"),
-        5 => Cow::Borrowed("6"),
-        6 => Cow::Borrowed("7"),
-        7 => Cow::Borrowed("8"),
-        8 => Cow::Borrowed("9"),
-        i => Cow::Owned((i + 1).to_string()),
+/// Suffix of indexed properties, the first index has none and the following ones start at 2.
+pub(super) struct Index(pub(super) u8);
+
+impl Display for Index {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            0 => Ok(()),
+            i => write!(f, "{}", u16::from(i) + 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_indexed_properties() {
+        // FuelVolume2..9 were read one index too high, so they were written to the next tank
+        for i in 0..9 {
+            for property in [Property::FuelWeight(i, 1.5), Property::FuelVolume(i, 1.5)] {
+                assert_eq!(Property::from_str(&property.to_string()).unwrap(), property);
+            }
+        }
+        assert_eq!(Property::FuelVolume(1, 1.5).to_string(), "FuelVolume2=1.5");
+        assert_eq!(
+            Property::from_str("FuelVolume1=1.5").unwrap(),
+            Property::FuelVolume(0, 1.5)
+        );
     }
 }
@@ -0,0 +1,556 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+#[cfg(feature = "bevy")]
+use bevy::prelude::{Component, ReflectComponent};
+#[cfg(feature = "bevy")]
+use bevy::reflect::{FromReflect, Reflect, ReflectRef};
+
+use super::{property::Index, Property};
+
+/// Identity of a [`Property`], two properties with the same key are the same property with
+/// possibly different values. Indexed properties like `FuelWeight3` have one key per index.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "bevy", derive(Reflect))]
+pub enum PropertyKey {
+    T,
+    Name,
+    Type,
+    Parent,
+    Next,
+    CallSign,
+    Registration,
+    Squawk,
+    ICAO24,
+    Pilot,
+    Group,
+    Country,
+    Coalition,
+    Color,
+    Shape,
+    Debug,
+    Label,
+    FocusedTarget,
+    LockedTarget,
+    Importance,
+    Slot,
+    Disabled,
+    Visible,
+    Health,
+    Length,
+    Width,
+    Height,
+    Radius,
+    IAS,
+    CAS,
+    TAS,
+    Mach,
+    AOA,
+    AOS,
+    AGL,
+    HDG,
+    HDM,
+    Throttle,
+    Afterburner,
+    AirBrakes,
+    Flaps,
+    LandingGear,
+    LandingGearHandle,
+    Tailhook,
+    Parachute,
+    DragChute,
+    FuelWeight(u8),
+    FuelVolume(u8),
+    FuelFlowWeight(u8),
+    FuelFlowVolume(u8),
+    RadarMode,
+    RadarAzimuth,
+    RadarElevation,
+    RadarRoll,
+    RadarRange,
+    RadarHorizontalBeamwidth,
+    RadarVerticalBeamwidth,
+    LockedTargetMode,
+    LockedTargetAzimuth,
+    LockedTargetElevation,
+    LockedTargetRange,
+    EngagementMode,
+    EngagementMode2,
+    EngagementRange,
+    EngagementRange2,
+    VerticalEngagementRange,
+    VerticalEngagementRange2,
+    RollControlInput,
+    PitchControlInput,
+    YawControlInput,
+    RollControlPosition,
+    PitchControlPosition,
+    YawControlPosition,
+    RollTrimTab,
+    PitchTrimTab,
+    YawTrimTab,
+    AileronLeft,
+    AileronRight,
+    Elevator,
+    Rudder,
+    PilotHeadRoll,
+    PilotHeadPitch,
+    PilotHeadYaw,
+    VerticalGForce,
+    LongitudinalGForce,
+    LateralGForce,
+    ENL,
+    Unknown(String),
+}
+
+impl Property {
+    pub fn key(&self) -> PropertyKey {
+        use Property::*;
+        match self {
+            T(_) => PropertyKey::T,
+            Name(_) => PropertyKey::Name,
+            Type(_) => PropertyKey::Type,
+            Parent(_) => PropertyKey::Parent,
+            Next(_) => PropertyKey::Next,
+            CallSign(_) => PropertyKey::CallSign,
+            Registration(_) => PropertyKey::Registration,
+            Squawk(_) => PropertyKey::Squawk,
+            ICAO24(_) => PropertyKey::ICAO24,
+            Pilot(_) => PropertyKey::Pilot,
+            Group(_) => PropertyKey::Group,
+            Country(_) => PropertyKey::Country,
+            Coalition(_) => PropertyKey::Coalition,
+            Color(_) => PropertyKey::Color,
+            Shape(_) => PropertyKey::Shape,
+            Debug(_) => PropertyKey::Debug,
+            Label(_) => PropertyKey::Label,
+            FocusedTarget(_) => PropertyKey::FocusedTarget,
+            LockedTarget(_) => PropertyKey::LockedTarget,
+            Importance(_) => PropertyKey::Importance,
+            Slot(_) => PropertyKey::Slot,
+            Disabled(_) => PropertyKey::Disabled,
+            Visible(_) => PropertyKey::Visible,
+            Health(_) => PropertyKey::Health,
+            Length(_) => PropertyKey::Length,
+            Width(_) => PropertyKey::Width,
+            Height(_) => PropertyKey::Height,
+            Radius(_) => PropertyKey::Radius,
+            IAS(_) => PropertyKey::IAS,
+            CAS(_) => PropertyKey::CAS,
+            TAS(_) => PropertyKey::TAS,
+            Mach(_) => PropertyKey::Mach,
+            AOA(_) => PropertyKey::AOA,
+            AOS(_) => PropertyKey::AOS,
+            AGL(_) => PropertyKey::AGL,
+            HDG(_) => PropertyKey::HDG,
+            HDM(_) => PropertyKey::HDM,
+            Throttle(_) => PropertyKey::Throttle,
+            Afterburner(_) => PropertyKey::Afterburner,
+            AirBrakes(_) => PropertyKey::AirBrakes,
+            Flaps(_) => PropertyKey::Flaps,
+            LandingGear(_) => PropertyKey::LandingGear,
+            LandingGearHandle(_) => PropertyKey::LandingGearHandle,
+            Tailhook(_) => PropertyKey::Tailhook,
+            Parachute(_) => PropertyKey::Parachute,
+            DragChute(_) => PropertyKey::DragChute,
+            FuelWeight(i, _) => PropertyKey::FuelWeight(*i),
+            FuelVolume(i, _) => PropertyKey::FuelVolume(*i),
+            FuelFlowWeight(i, _) => PropertyKey::FuelFlowWeight(*i),
+            FuelFlowVolume(i, _) => PropertyKey::FuelFlowVolume(*i),
+            RadarMode(_) => PropertyKey::RadarMode,
+            RadarAzimuth(_) => PropertyKey::RadarAzimuth,
+            RadarElevation(_) => PropertyKey::RadarElevation,
+            RadarRoll(_) => PropertyKey::RadarRoll,
+            RadarRange(_) => PropertyKey::RadarRange,
+            RadarHorizontalBeamwidth(_) => PropertyKey::RadarHorizontalBeamwidth,
+            RadarVerticalBeamwidth(_) => PropertyKey::RadarVerticalBeamwidth,
+            LockedTargetMode(_) => PropertyKey::LockedTargetMode,
+            LockedTargetAzimuth(_) => PropertyKey::LockedTargetAzimuth,
+            LockedTargetElevation(_) => PropertyKey::LockedTargetElevation,
+            LockedTargetRange(_) => PropertyKey::LockedTargetRange,
+            EngagementMode(_) => PropertyKey::EngagementMode,
+            EngagementMode2(_) => PropertyKey::EngagementMode2,
+            EngagementRange(_) => PropertyKey::EngagementRange,
+            EngagementRange2(_) => PropertyKey::EngagementRange2,
+            VerticalEngagementRange(_) => PropertyKey::VerticalEngagementRange,
+            VerticalEngagementRange2(_) => PropertyKey::VerticalEngagementRange2,
+            RollControlInput(_) => PropertyKey::RollControlInput,
+            PitchControlInput(_) => PropertyKey::PitchControlInput,
+            YawControlInput(_) => PropertyKey::YawControlInput,
+            RollControlPosition(_) => PropertyKey::RollControlPosition,
+            PitchControlPosition(_) => PropertyKey::PitchControlPosition,
+            YawControlPosition(_) => PropertyKey::YawControlPosition,
+            RollTrimTab(_) => PropertyKey::RollTrimTab,
+            PitchTrimTab(_) => PropertyKey::PitchTrimTab,
+            YawTrimTab(_) => PropertyKey::YawTrimTab,
+            AileronLeft(_) => PropertyKey::AileronLeft,
+            AileronRight(_) => PropertyKey::AileronRight,
+            Elevator(_) => PropertyKey::Elevator,
+            Rudder(_) => PropertyKey::Rudder,
+            PilotHeadRoll(_) => PropertyKey::PilotHeadRoll,
+            PilotHeadPitch(_) => PropertyKey::PilotHeadPitch,
+            PilotHeadYaw(_) => PropertyKey::PilotHeadYaw,
+            VerticalGForce(_) => PropertyKey::VerticalGForce,
+            LongitudinalGForce(_) => PropertyKey::LongitudinalGForce,
+            LateralGForce(_) => PropertyKey::LateralGForce,
+            ENL(_) => PropertyKey::ENL,
+            Unknown(name, _) => PropertyKey::Unknown(name.clone()),
+        }
+    }
+}
+
+/// The name of the property as written in ACMI, e.g. `FuelWeight3`.
+impl Display for PropertyKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use PropertyKey::*;
+        match self {
+            T => f.write_str("T"),
+            Name => f.write_str("Name"),
+            Type => f.write_str("Type"),
+            Parent => f.write_str("Parent"),
+            Next => f.write_str("Next"),
+            CallSign => f.write_str("CallSign"),
+            Registration => f.write_str("Registration"),
+            Squawk => f.write_str("Squawk"),
+            ICAO24 => f.write_str("ICAO24"),
+            Pilot => f.write_str("Pilot"),
+            Group => f.write_str("Group"),
+            Country => f.write_str("Country"),
+            Coalition => f.write_str("Coalition"),
+            Color => f.write_str("Color"),
+            Shape => f.write_str("Shape"),
+            Debug => f.write_str("Debug"),
+            Label => f.write_str("Label"),
+            FocusedTarget => f.write_str("FocusedTarget"),
+            LockedTarget => f.write_str("LockedTarget"),
+            Importance => f.write_str("Importance"),
+            Slot => f.write_str("Slot"),
+            Disabled => f.write_str("Disabled"),
+            Visible => f.write_str("Visible"),
+            Health => f.write_str("Health"),
+            Length => f.write_str("Length"),
+            Width => f.write_str("Width"),
+            Height => f.write_str("Height"),
+            Radius => f.write_str("Radius"),
+            IAS => f.write_str("IAS"),
+            CAS => f.write_str("CAS"),
+            TAS => f.write_str("TAS"),
+            Mach => f.write_str("Mach"),
+            AOA => f.write_str("AOA"),
+            AOS => f.write_str("AOS"),
+            AGL => f.write_str("AGL"),
+            HDG => f.write_str("HDG"),
+            HDM => f.write_str("HDM"),
+            Throttle => f.write_str("Throttle"),
+            Afterburner => f.write_str("Afterburner"),
+            AirBrakes => f.write_str("AirBrakes"),
+            Flaps => f.write_str("Flaps"),
+            LandingGear => f.write_str("LandingGear"),
+            LandingGearHandle => f.write_str("LandingGearHandle"),
+            Tailhook => f.write_str("Tailhook"),
+            Parachute => f.write_str("Parachute"),
+            DragChute => f.write_str("DragChute"),
+            FuelWeight(i) => write!(f, "FuelWeight{}", Index(*i)),
+            FuelVolume(i) => write!(f, "FuelVolume{}", Index(*i)),
+            FuelFlowWeight(i) => write!(f, "FuelFlowWeight{}", Index(*i)),
+            FuelFlowVolume(i) => write!(f, "FuelFlowVolume{}", Index(*i)),
+            RadarMode => f.write_str("RadarMode"),
+            RadarAzimuth => f.write_str("RadarAzimuth"),
+            RadarElevation => f.write_str("RadarElevation"),
+            RadarRoll => f.write_str("RadarRoll"),
+            RadarRange => f.write_str("RadarRange"),
+            RadarHorizontalBeamwidth => f.write_str("RadarHorizontalBeamwidth"),
+            RadarVerticalBeamwidth => f.write_str("RadarVerticalBeamwidth"),
+            LockedTargetMode => f.write_str("LockedTargetMode"),
+            LockedTargetAzimuth => f.write_str("LockedTargetAzimuth"),
+            LockedTargetElevation => f.write_str("LockedTargetElevation"),
+            LockedTargetRange => f.write_str("LockedTargetRange"),
+            EngagementMode => f.write_str("EngagementMode"),
+            EngagementMode2 => f.write_str("EngagementMode2"),
+            EngagementRange => f.write_str("EngagementRange"),
+            EngagementRange2 => f.write_str("EngagementRange2"),
+            VerticalEngagementRange => f.write_str("VerticalEngagementRange"),
+            VerticalEngagementRange2 => f.write_str("VerticalEngagementRange2"),
+            RollControlInput => f.write_str("RollControlInput"),
+            PitchControlInput => f.write_str("PitchControlInput"),
+            YawControlInput => f.write_str("YawControlInput"),
+            RollControlPosition => f.write_str("RollControlPosition"),
+            PitchControlPosition => f.write_str("PitchControlPosition"),
+            YawControlPosition => f.write_str("YawControlPosition"),
+            RollTrimTab => f.write_str("RollTrimTab"),
+            PitchTrimTab => f.write_str("PitchTrimTab"),
+            YawTrimTab => f.write_str("YawTrimTab"),
+            AileronLeft => f.write_str("AileronLeft"),
+            AileronRight => f.write_str("AileronRight"),
+            Elevator => f.write_str("Elevator"),
+            Rudder => f.write_str("Rudder"),
+            PilotHeadRoll => f.write_str("PilotHeadRoll"),
+            PilotHeadPitch => f.write_str("PilotHeadPitch"),
+            PilotHeadYaw => f.write_str("PilotHeadYaw"),
+            VerticalGForce => f.write_str("VerticalGForce"),
+            LongitudinalGForce => f.write_str("LongitudinalGForce"),
+            LateralGForce => f.write_str("LateralGForce"),
+            ENL => f.write_str("ENL"),
+            Unknown(name) => f.write_str(name),
+        }
+    }
+}
+
+/// Properties of an object keyed by [`PropertyKey`], with the keys changed since the last sync.
+///
+/// Properties are merged as Tacview applies updates: a property replaces the previous value with
+/// the same key, except `T` where only the given coordinates replace the previous ones.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "bevy",
+    derive(Component, Reflect),
+    reflect(Component, from_reflect = false)
+)]
+pub struct PropertyList {
+    /// At most one property per key, in the order they were first set.
+    props: Vec<Property>,
+    /// Position of each key in `props`, rebuilt by `FromReflect`.
+    #[cfg_attr(feature = "bevy", reflect(ignore))]
+    index: BTreeMap<PropertyKey, usize>,
+    #[cfg_attr(feature = "bevy", reflect(ignore))]
+    changed: BTreeSet<PropertyKey>,
+}
+
+impl PropertyList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.props.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.props.is_empty()
+    }
+
+    pub fn contains(&self, key: &PropertyKey) -> bool {
+        self.position(key).is_some()
+    }
+
+    pub fn get(&self, key: &PropertyKey) -> Option<&Property> {
+        self.props.get(self.position(key)?)
+    }
+
+    /// The value of a property with a numeric value, see [`Property::as_f64`].
+    pub fn get_f64(&self, key: &PropertyKey) -> Option<f64> {
+        self.get(key)?.as_f64()
+    }
+
+    /// Set a property, replacing its previous value which is returned.
+    pub fn set(&mut self, property: Property) -> Option<Property> {
+        let key = property.key();
+        match self.position(&key) {
+            Some(i) => {
+                if self.props[i] != property {
+                    self.changed.insert(key);
+                }
+                Some(std::mem::replace(&mut self.props[i], property))
+            }
+            None => {
+                self.changed.insert(key.clone());
+                self.index.insert(key, self.props.len());
+                self.props.push(property);
+                None
+            }
+        }
+    }
+
+    /// Merge a property as Tacview does when it receives an update.
+    pub fn merge(&mut self, property: Property) {
+        let current = self.position(&PropertyKey::T).map(|i| &mut self.props[i]);
+        match (current, property) {
+            (Some(Property::T(coords)), Property::T(other)) => {
+                let previous = coords.clone();
+                coords.update(&other, 0.0, 0.0);
+                if *coords != previous {
+                    self.changed.insert(PropertyKey::T);
+                }
+            }
+            (_, property) => {
+                self.set(property);
+            }
+        }
+    }
+
+    /// Remove a property. ACMI cannot unset a property, peers keep its last value.
+    pub fn remove(&mut self, key: &PropertyKey) -> Option<Property> {
+        self.changed.remove(key);
+        let removed = self.index.remove(key)?;
+        for i in self.index.values_mut() {
+            if *i > removed {
+                *i -= 1;
+            }
+        }
+        Some(self.props.remove(removed))
+    }
+
+    /// Properties in the order they were first set.
+    pub fn iter(&self) -> std::slice::Iter<'_, Property> {
+        self.props.iter()
+    }
+
+    /// Properties set or merged with a different value since the last [`Self::clear_changes`].
+    pub fn changes(&self) -> impl Iterator<Item = &Property> {
+        self.changed.iter().filter_map(|key| self.get(key))
+    }
+
+    pub fn has_changes(&self) -> bool {
+        !self.changed.is_empty()
+    }
+
+    /// Mark all properties as synced.
+    pub fn clear_changes(&mut self) {
+        self.changed.clear();
+    }
+
+    fn position(&self, key: &PropertyKey) -> Option<usize> {
+        self.index.get(key).copied()
+    }
+}
+
+/// Lists are equal if they have the same properties in any order, regardless of their changes.
+impl PartialEq for PropertyList {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .index
+                .iter()
+                .all(|(key, &i)| other.get(key) == Some(&self.props[i]))
+    }
+}
+
+/// Only the properties are reflected, the list has no changes.
+#[cfg(feature = "bevy")]
+impl FromReflect for PropertyList {
+    fn from_reflect(reflect: &dyn Reflect) -> Option<Self> {
+        let ReflectRef::Struct(list) = reflect.reflect_ref() else {
+            return None;
+        };
+        let props = Vec::<Property>::from_reflect(list.field("props")?)?;
+        let index = props
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (p.key(), i))
+            .collect();
+        Some(Self {
+            props,
+            index,
+            changed: BTreeSet::new(),
+        })
+    }
+}
+
+impl Extend<Property> for PropertyList {
+    fn extend<I: IntoIterator<Item = Property>>(&mut self, iter: I) {
+        for property in iter {
+            self.merge(property);
+        }
+    }
+}
+
+impl FromIterator<Property> for PropertyList {
+    fn from_iter<I: IntoIterator<Item = Property>>(iter: I) -> Self {
+        let mut list = Self::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl From<Vec<Property>> for PropertyList {
+    fn from(props: Vec<Property>) -> Self {
+        props.into_iter().collect()
+    }
+}
+
+impl<'a> IntoIterator for &'a PropertyList {
+    type Item = &'a Property;
+    type IntoIter = std::slice::Iter<'a, Property>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::Coords;
+
+    #[test]
+    fn test_property_list() {
+        let mut list = PropertyList::from(vec![
+            Property::Health(1.0),
+            Property::FuelWeight(2, 1000.0),
+            Property::Unknown("Custom".to_string(), "a".to_string()),
+            Property::T(Coords::default().position(1.0, 2.0, 3.0)),
+        ]);
+        list.clear_changes();
+
+        assert_eq!(PropertyKey::FuelWeight(2).to_string(), "FuelWeight3");
+        assert_eq!(list.get_f64(&PropertyKey::FuelWeight(2)), Some(1000.0));
+        assert_eq!(list.get(&PropertyKey::FuelWeight(1)), None);
+
+        assert_eq!(list.set(Property::Health(1.0)), Some(Property::Health(1.0)));
+        assert!(!list.has_changes());
+
+        list.extend([
+            Property::Health(0.5),
+            Property::FuelWeight(1, 500.0),
+            Property::Unknown("Custom".to_string(), "b".to_string()),
+            Property::T(Coords {
+                altitude: Some(4.0),
+                ..Default::default()
+            }),
+        ]);
+        assert_eq!(list.len(), 5);
+        assert_eq!(
+            list.get(&PropertyKey::T),
+            Some(&Property::T(Coords::default().position(1.0, 2.0, 4.0)))
+        );
+        assert_eq!(
+            list.get(&PropertyKey::Unknown("Custom".to_string())),
+            Some(&Property::Unknown("Custom".to_string(), "b".to_string()))
+        );
+        assert_eq!(list.changes().count(), 4);
+
+        assert_eq!(
+            list.remove(&PropertyKey::Health),
+            Some(Property::Health(0.5))
+        );
+        assert_eq!(list.changes().count(), 3);
+        list.clear_changes();
+        assert!(!list.has_changes());
+    }
+
+    #[cfg(feature = "bevy")]
+    #[test]
+    fn test_reflect() {
+        use bevy::reflect::FromReflect;
+
+        use crate::record::Tag;
+
+        let list = PropertyList::from(vec![
+            Property::Name("F-16C".to_string()),
+            Property::Type([Tag::Air, Tag::FixedWing].into()),
+            Property::FuelWeight(2, 1000.0),
+            Property::T(Coords::default().position(1.0, 2.0, 3.0)),
+        ]);
+        let reflected = list.clone_value();
+        let list = PropertyList::from_reflect(&*reflected).unwrap();
+        assert_eq!(
+            list.get(&PropertyKey::Type),
+            Some(&Property::Type([Tag::Air, Tag::FixedWing].into()))
+        );
+        assert_eq!(list.get_f64(&PropertyKey::FuelWeight(2)), Some(1000.0));
+        assert_eq!(list.len(), 4);
+    }
+}
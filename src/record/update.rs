@@ -1,10 +1,15 @@
 use std::{fmt::Display, str::FromStr};
 
+#[cfg(feature = "bevy")]
+use bevy::reflect::Reflect;
+
 use super::Property;
 use crate::ParseError;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "bevy", derive(Reflect))]
 pub struct Update {
+    /// Object id, written in hexadecimal as it is parsed.
     pub id: u64,
     pub props: Vec<Property>,
 }
@@ -13,35 +18,54 @@ impl FromStr for Update {
     type Err = ParseError;
 
     fn from_str(line: &str) -> Result<Self, Self::Err> {
-        let (id, mut rest) = line.split_once(',').ok_or(ParseError::Eol)?;
+        let (id, rest) = line.split_once(',').ok_or(ParseError::Eol)?;
         let id = u64::from_str_radix(id, 16)?;
-        let mut props = Vec::new();
+        let props = SplitProps::new(rest)
+            .map(Property::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Update { id, props })
+    }
+}
+
+/// Splits the properties of an update line at each comma not escaped by a backslash.
+#[derive(Debug, Clone)]
+pub(crate) struct SplitProps<'a> {
+    rest: &'a str,
+}
+
+impl<'a> SplitProps<'a> {
+    pub(crate) fn new(props: &'a str) -> Self {
+        Self { rest: props }
+    }
+}
+
+impl<'a> Iterator for SplitProps<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
 
         let mut prev = None;
-        let mut offset = 0;
-        for (i, ch) in rest.char_indices() {
+        for (i, ch) in self.rest.char_indices() {
             if ch == ',' && prev != Some('\\') {
-                let (kv, r) = rest.split_at(i - offset);
-                rest = r.strip_prefix(',').unwrap_or(rest);
-                offset = i + 1;
-
-                props.push(Property::from_str(kv)?);
+                let (kv, rest) = self.rest.split_at(i);
+                self.rest = &rest[1..];
+                return Some(kv);
             }
 
             prev = Some(ch);
         }
 
-        if !rest.is_empty() {
-            props.push(Property::from_str(rest)?);
-        }
-
-        Ok(Update { id, props })
+        Some(std::mem::take(&mut self.rest))
     }
 }
 
 impl Display for Update {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.id)?;
+        write!(f, "{:x}", self.id)?;
         for p in &self.props {
             write!(f, ",{p}")?;
         }
@@ -0,0 +1,358 @@
+//! Destinations of the telemetry stream.
+//!
+//! Every entity with a [`Sink`] receives the header of the stream followed by a frame per tick,
+//! starting with a full sync of the objects. Network peers get a sink when they connect, other
+//! destinations are added by spawning one:
+//!
+//! ```rust,ignore
+//! commands.spawn(Sink::new(FileSink::create("flight.txt.acmi")?));
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use crossbeam_channel::{Receiver, Sender, TrySendError};
+
+/// Receives the serialized ACMI stream.
+pub trait TacviewSink: Send + Sync + 'static {
+    /// Send the header or a frame of the stream.
+    fn send(&mut self, data: &[u8]) -> Result<(), io::Error>;
+
+    /// Bytes stored by a recorder, reported by the `tacview/recorder_file_size` diagnostic.
+    fn recorded_size(&self) -> Option<u64> {
+        None
+    }
+
+    /// Frames sent but not delivered yet, a sink with more than
+    /// [`PeerQueueLimits`](crate::systems::PeerQueueLimits) is lagging and its queued frames are
+    /// dropped.
+    fn queued(&self) -> usize {
+        0
+    }
+
+    /// Drop the frames sent but not delivered yet, returns how many were dropped. Called when the
+    /// sink is lagging, it gets a full sync right away if nothing is left queued.
+    fn drop_queued(&mut self) -> usize {
+        0
+    }
+}
+
+/// Destination of the telemetry stream of its entity.
+#[derive(Component)]
+pub struct Sink(Box<dyn TacviewSink>);
+
+impl Sink {
+    pub fn new(sink: impl TacviewSink) -> Self {
+        Self(Box::new(sink))
+    }
+
+    pub fn send(&mut self, data: &[u8]) -> Result<(), io::Error> {
+        self.0.send(data)
+    }
+
+    pub fn recorded_size(&self) -> Option<u64> {
+        self.0.recorded_size()
+    }
+
+    pub fn queued(&self) -> usize {
+        self.0.queued()
+    }
+
+    pub fn drop_queued(&mut self) -> usize {
+        self.0.drop_queued()
+    }
+}
+
+/// Records the stream to a `.txt.acmi` file.
+pub struct FileSink {
+    wr: BufWriter<File>,
+    size: u64,
+}
+
+impl FileSink {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        Ok(Self {
+            wr: BufWriter::new(File::create(path)?),
+            size: 0,
+        })
+    }
+}
+
+impl TacviewSink for FileSink {
+    fn send(&mut self, data: &[u8]) -> Result<(), io::Error> {
+        self.wr.write_all(data)?;
+        self.wr.flush()?;
+        self.size += data.len() as u64;
+        Ok(())
+    }
+
+    fn recorded_size(&self) -> Option<u64> {
+        Some(self.size)
+    }
+}
+
+/// Keeps the stream in memory, clones share the same buffer.
+#[derive(Clone, Default)]
+pub struct MemorySink(Arc<Mutex<Vec<u8>>>);
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Everything sent so far.
+    pub fn contents(&self) -> Vec<u8> {
+        self.0.lock().map(|buf| buf.clone()).unwrap_or_default()
+    }
+
+    /// Take everything sent so far, leaving the buffer empty.
+    pub fn take(&self) -> Vec<u8> {
+        self.0
+            .lock()
+            .map(|mut buf| std::mem::take(&mut *buf))
+            .unwrap_or_default()
+    }
+}
+
+impl TacviewSink for MemorySink {
+    fn send(&mut self, data: &[u8]) -> Result<(), io::Error> {
+        let mut buf = self
+            .0
+            .lock()
+            .map_err(|_| io::Error::other("memory sink poisoned"))?;
+        buf.extend_from_slice(data);
+        Ok(())
+    }
+}
+
+/// Sends every part of the stream through a channel, e.g. to another thread.
+pub struct ChannelSink(Sender<Vec<u8>>);
+
+impl ChannelSink {
+    pub fn new(sender: Sender<Vec<u8>>) -> Self {
+        Self(sender)
+    }
+
+    /// A sink with an unbounded channel, and the receiving end.
+    pub fn unbounded() -> (Self, Receiver<Vec<u8>>) {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        (Self(tx), rx)
+    }
+
+    /// A sink with a channel of `capacity` parts, sending fails while it is full.
+    pub fn bounded(capacity: usize) -> (Self, Receiver<Vec<u8>>) {
+        let (tx, rx) = crossbeam_channel::bounded(capacity);
+        (Self(tx), rx)
+    }
+}
+
+impl TacviewSink for ChannelSink {
+    fn send(&mut self, data: &[u8]) -> Result<(), io::Error> {
+        self.0.try_send(data.to_vec()).map_err(|err| match err {
+            TrySendError::Full(_) => io::Error::new(io::ErrorKind::WouldBlock, "channel full"),
+            TrySendError::Disconnected(_) => {
+                io::Error::new(io::ErrorKind::BrokenPipe, "channel disconnected")
+            }
+        })
+    }
+
+    fn queued(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// A bounded [`ChannelSink`] which can drop the frames its receiver did not take yet.
+///
+/// It holds a receiver of its channel, so sending does not fail once the other receiver is
+/// dropped, the owner of the receiver notices disconnections.
+pub(crate) struct QueueSink {
+    sink: ChannelSink,
+    queue: Receiver<Vec<u8>>,
+    /// Parts sent since the header, until it is known to be delivered.
+    sent: Option<usize>,
+}
+
+impl QueueSink {
+    pub(crate) fn new(capacity: usize) -> (Self, Receiver<Vec<u8>>) {
+        let (sink, rx) = ChannelSink::bounded(capacity);
+        let queue = rx.clone();
+        (
+            Self {
+                sink,
+                queue,
+                sent: Some(0),
+            },
+            rx,
+        )
+    }
+}
+
+impl TacviewSink for QueueSink {
+    fn send(&mut self, data: &[u8]) -> Result<(), io::Error> {
+        self.sink.send(data)?;
+        if let Some(sent) = &mut self.sent {
+            *sent += 1;
+        }
+        Ok(())
+    }
+
+    fn queued(&self) -> usize {
+        self.sink.queued()
+    }
+
+    fn drop_queued(&mut self) -> usize {
+        // the header is the first part sent, it is kept until it is delivered
+        let header = match self.sent {
+            Some(sent) if self.queue.len() == sent => self.queue.try_recv().ok(),
+            _ => None,
+        };
+        let dropped = self.queue.try_iter().count();
+        self.sent = None;
+        if let Some(header) = header {
+            self.sent = Some(0);
+            self.send(&header).ok();
+        }
+        dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::Property;
+    use crate::systems::{Lagging, ObjectNeedSync, PeerLagging, PeerQueueLimits};
+    use crate::test_utils::{spawn_memory_peer, spawn_object, test_app};
+
+    #[test]
+    fn test_memory_sink() {
+        let mut app = test_app();
+        let (_, memory) = spawn_memory_peer(&mut app.world);
+        spawn_object(&mut app.world, vec![Property::Health(0.5)]);
+        app.update();
+
+        let stream = String::from_utf8(memory.take()).unwrap();
+        assert!(stream.starts_with("FileType=text/acmi/tacview\nFileVersion=2.2\n"));
+        assert!(stream.contains(",Health=0.5\n"));
+
+        let (sink, rx) = ChannelSink::unbounded();
+        app.world.spawn(Sink::new(sink));
+        app.update();
+        // a sink added later starts with its own header
+        let stream = String::from_utf8(rx.try_iter().flatten().collect()).unwrap();
+        assert!(stream.starts_with("FileType=text/acmi/tacview\n"));
+        assert!(stream.contains("\n#"));
+    }
+
+    #[test]
+    fn test_bounded_channel_sink() {
+        let (mut sink, rx) = ChannelSink::bounded(2);
+        sink.send(b"a").unwrap();
+        sink.send(b"b").unwrap();
+        assert_eq!(sink.queued(), 2);
+        let err = sink.send(b"c").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+        assert_eq!(rx.recv().unwrap(), b"a");
+        assert_eq!(sink.queued(), 1);
+        drop(rx);
+        let err = sink.send(b"c").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn test_lagging() {
+        let mut app = test_app();
+        app.insert_resource(PeerQueueLimits {
+            max_queued_frames: 2,
+        });
+        let (sink, rx) = ChannelSink::unbounded();
+        let peer = app.world.spawn(Sink::new(sink)).id();
+        let object = spawn_object(&mut app.world, vec![Property::Health(0.5)]);
+        let destroyed = spawn_object(&mut app.world, vec![Property::Health(0.5)]);
+        // the header and the full sync are queued
+        app.update();
+        assert_eq!(rx.len(), 2);
+
+        app.world.entity_mut(object).insert(ObjectNeedSync::Update);
+        app.update();
+        assert!(app.world.get::<Lagging>(peer).is_some());
+        let mut lagging = app.world.resource_mut::<Events<PeerLagging>>();
+        assert_eq!(
+            lagging.drain().collect::<Vec<_>>(),
+            [PeerLagging { peer, queued: 2 }]
+        );
+
+        app.world
+            .entity_mut(destroyed)
+            .insert(ObjectNeedSync::Destroy);
+        app.update();
+        app.world.despawn(destroyed);
+        assert_eq!(rx.len(), 2);
+
+        // once caught up the peer gets a full sync without the destroyed object
+        rx.try_iter().for_each(drop);
+        app.update();
+        assert!(app.world.get::<Lagging>(peer).is_none());
+        let stream = String::from_utf8(rx.try_iter().flatten().collect()).unwrap();
+        let lines = stream.lines().collect::<Vec<_>>();
+        assert!(lines.contains(&format!("-{:x}", destroyed.to_bits()).as_str()));
+        assert!(lines.contains(&format!("{:x},T=||,Health=0.5", object.to_bits()).as_str()));
+        assert!(!stream.contains(&format!("{:x},T=", destroyed.to_bits())));
+    }
+
+    #[test]
+    fn test_drop_queued() {
+        let mut app = test_app();
+        app.insert_resource(PeerQueueLimits {
+            max_queued_frames: 3,
+        });
+        let (sink, rx) = QueueSink::new(3);
+        let peer = app.world.spawn(Sink::new(sink)).id();
+        let object = spawn_object(&mut app.world, vec![Property::Health(0.5)]);
+        let destroyed = spawn_object(&mut app.world, vec![Property::Health(0.5)]);
+        app.update();
+        let header = String::from_utf8(rx.recv().unwrap()).unwrap();
+        assert!(header.starts_with("FileType=text/acmi/tacview\n"));
+
+        app.world
+            .entity_mut(destroyed)
+            .insert(ObjectNeedSync::Destroy);
+        app.update();
+        app.world.despawn(destroyed);
+        app.world.entity_mut(object).insert(ObjectNeedSync::Update);
+        app.update();
+        assert_eq!(rx.len(), 3);
+
+        // the queued frames are dropped for a full sync right away
+        app.world.entity_mut(object).insert(ObjectNeedSync::Update);
+        app.update();
+        assert!(app.world.get::<Lagging>(peer).is_none());
+        let mut lagging = app.world.resource_mut::<Events<PeerLagging>>();
+        assert_eq!(
+            lagging.drain().collect::<Vec<_>>(),
+            [PeerLagging { peer, queued: 3 }]
+        );
+        assert_eq!(rx.len(), 1);
+        let stream = String::from_utf8(rx.recv().unwrap()).unwrap();
+        let lines = stream.lines().collect::<Vec<_>>();
+        assert!(lines.contains(&format!("-{:x}", destroyed.to_bits()).as_str()));
+        assert!(lines.contains(&format!("{:x},T=||,Health=0.5", object.to_bits()).as_str()));
+    }
+
+    #[test]
+    fn test_drop_queued_keeps_header() {
+        let (mut sink, rx) = QueueSink::new(3);
+        sink.send(b"header").unwrap();
+        sink.send(b"frame").unwrap();
+        assert_eq!(sink.drop_queued(), 1);
+        assert_eq!(sink.queued(), 1);
+        assert_eq!(rx.recv().unwrap(), b"header");
+
+        sink.send(b"frame").unwrap();
+        assert_eq!(sink.drop_queued(), 1);
+        assert_eq!(sink.queued(), 0);
+    }
+}
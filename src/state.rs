@@ -0,0 +1,231 @@
+//! Reconstruction of the state of all objects by replaying records.
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{
+    geo::{Bra, Braa},
+    record::{Coords, GlobalProperty, Property, PropertyKey, PropertyList, Record, Tag},
+};
+
+/// The latest known state of an object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectState {
+    pub id: u64,
+    /// Coordinates with `ReferenceLatitude` and `ReferenceLongitude` applied.
+    pub coords: Coords,
+    /// The latest value of every property received, except `T`.
+    pub props: PropertyList,
+    /// Time of the frame the object was first updated in.
+    pub first_seen: f64,
+    /// Time of the frame the object was last updated in.
+    pub last_seen: f64,
+}
+
+/// The state of a recording at the current frame.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorldState {
+    /// Time of the current frame, in seconds since `ReferenceTime`.
+    pub time: f64,
+    pub reference_latitude: f64,
+    pub reference_longitude: f64,
+    pub globals: Vec<GlobalProperty>,
+    pub objects: BTreeMap<u64, ObjectState>,
+}
+
+impl WorldState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply a record, returns the last state of the object removed by it, if any.
+    pub fn apply(&mut self, record: &Record) -> Option<ObjectState> {
+        match record {
+            Record::GlobalProperty(p) => {
+                match p {
+                    GlobalProperty::ReferenceLatitude(v) => self.reference_latitude = *v,
+                    GlobalProperty::ReferenceLongitude(v) => self.reference_longitude = *v,
+                    _ => {}
+                }
+                match self.globals.iter_mut().find(|g| same_global(g, p)) {
+                    Some(g) => *g = p.clone(),
+                    None => self.globals.push(p.clone()),
+                }
+            }
+            Record::Frame(time) => self.time = *time,
+            Record::Remove(id) => return self.objects.remove(id),
+            Record::Event(_) => {}
+            Record::Update(update) => {
+                let object = self
+                    .objects
+                    .entry(update.id)
+                    .or_insert_with(|| ObjectState {
+                        id: update.id,
+                        coords: Coords::default(),
+                        props: PropertyList::new(),
+                        first_seen: self.time,
+                        last_seen: self.time,
+                    });
+                object.last_seen = self.time;
+                for p in &update.props {
+                    match p {
+                        Property::T(coords) => object.coords.update(
+                            coords,
+                            self.reference_latitude,
+                            self.reference_longitude,
+                        ),
+                        p => object.props.merge(p.clone()),
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    pub fn get(&self, id: u64) -> Option<&ObjectState> {
+        self.objects.get(&id)
+    }
+
+    /// Objects tagged `Bullseye`.
+    pub fn bullseyes(&self) -> impl Iterator<Item = &ObjectState> {
+        self.objects.values().filter(|o| o.has_tag(&Tag::Bullseye))
+    }
+
+    /// Bearing, range and altitude of `target` from the `bullseye` object.
+    pub fn bra(&self, bullseye: u64, target: u64) -> Option<Bra> {
+        self.get(bullseye)?.coords.bra(&self.get(target)?.coords)
+    }
+
+    /// Bearing, range, altitude and aspect of `target` from the `viewer` object.
+    pub fn braa(&self, viewer: u64, target: u64) -> Option<Braa> {
+        self.get(viewer)?.coords.braa(&self.get(target)?.coords)
+    }
+
+    /// Coordinates of an object relative to `ReferenceLatitude` and `ReferenceLongitude`, as they
+    /// would be written in a recording.
+    pub fn relative_coords(&self, object: &ObjectState) -> Coords {
+        let mut coords = object.coords.clone();
+        coords.latitude = coords.latitude.map(|v| v - self.reference_latitude);
+        coords.longitude = coords.longitude.map(|v| v - self.reference_longitude);
+        coords
+    }
+}
+
+impl ObjectState {
+    pub fn tags(&self) -> Option<&HashSet<Tag>> {
+        match self.props.get(&PropertyKey::Type)? {
+            Property::Type(tags) => Some(tags),
+            _ => None,
+        }
+    }
+
+    pub fn has_tag(&self, tag: &Tag) -> bool {
+        self.tags().is_some_and(|tags| tags.contains(tag))
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.find(|p| match p {
+            Property::Name(v) => Some(v.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn call_sign(&self) -> Option<&str> {
+        self.find(|p| match p {
+            Property::CallSign(v) => Some(v.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn pilot(&self) -> Option<&str> {
+        self.find(|p| match p {
+            Property::Pilot(v) => Some(v.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn group(&self) -> Option<&str> {
+        self.find(|p| match p {
+            Property::Group(v) => Some(v.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn coalition(&self) -> Option<&str> {
+        self.find(|p| match p {
+            Property::Coalition(v) => Some(v.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn parent(&self) -> Option<u64> {
+        self.find(|p| match p {
+            Property::Parent(v) => Some(*v),
+            _ => None,
+        })
+    }
+
+    pub fn locked_target(&self) -> Option<u64> {
+        self.find(|p| match p {
+            Property::LockedTarget(v) => Some(*v),
+            _ => None,
+        })
+    }
+
+    /// Find the first property `f` returns a value for.
+    pub fn find<'a, T>(&'a self, f: impl FnMut(&'a Property) -> Option<T>) -> Option<T> {
+        self.props.iter().find_map(f)
+    }
+}
+
+fn same_global(a: &GlobalProperty, b: &GlobalProperty) -> bool {
+    match (a, b) {
+        (GlobalProperty::Unknown(a, _), GlobalProperty::Unknown(b, _)) => a == b,
+        (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::Update;
+
+    #[test]
+    fn test_apply() {
+        let mut state = WorldState::new();
+        state.apply(&GlobalProperty::ReferenceLatitude(40.0).into());
+        state.apply(&Record::Frame(1.0));
+        state.apply(
+            &Update {
+                id: 0xa0,
+                props: vec![
+                    Property::T(Coords::default().position(1.0, 2.0, 3.0)),
+                    Property::Health(1.0),
+                ],
+            }
+            .into(),
+        );
+        state.apply(&Record::Frame(2.0));
+        state.apply(
+            &Update {
+                id: 0xa0,
+                props: vec![
+                    Property::T(Coords {
+                        altitude: Some(4.0),
+                        ..Default::default()
+                    }),
+                    Property::Health(0.5),
+                ],
+            }
+            .into(),
+        );
+
+        let object = state.get(0xa0).unwrap();
+        assert_eq!(object.coords, Coords::default().position(41.0, 2.0, 4.0));
+        assert_eq!(object.props, vec![Property::Health(0.5)].into());
+        assert_eq!((object.first_seen, object.last_seen), (1.0, 2.0));
+
+        let removed = state.apply(&Record::Remove(0xa0)).unwrap();
+        assert_eq!(removed.id, 0xa0);
+        assert!(state.objects.is_empty());
+    }
+}
@@ -1,13 +1,19 @@
 use bevy::prelude::*;
-use bevy_octopus::connections::NetworkPeer;
-use bevy_octopus::prelude::{ChannelId, NetworkNode};
-use bevy_octopus::shared::{NetworkEvent, NetworkNodeEvent};
+use bevy::utils::Instant;
 use chrono::{DateTime, SecondsFormat, Utc};
+use std::collections::VecDeque;
+use std::io;
 
-use crate::record::PropertyList;
-use crate::{record::{Coords, Event, EventKind, GlobalProperty, Property, Record, Update}, TACVIEW_CHANNEL, Writer};
+use crate::analysis::TakeoffDetector;
+use crate::components::TacviewComponent;
+use crate::diagnostics::SyncStats;
+use crate::geo::{Bra, Braa};
+use crate::record::{PropertyKey, PropertyList};
+use crate::sink::Sink;
+use crate::{record::{Coords, Event, EventKind, GlobalProperty, Property, Record, Update}, Writer};
 
-static REAL_TIME_PROTOCOL: &str = "XtraLib.Stream.0
+/// Handshake of the real-time telemetry protocol, sent by the host when a client connects.
+pub static REAL_TIME_PROTOCOL: &str = "XtraLib.Stream.0
 Tacview.RealTimeTelemetry.0
 Host hoevo
 \0";
@@ -46,106 +52,247 @@ pub struct TacviewResource {
     pub data_recorder: String,
 }
 
-/// send header after connected
-pub(crate) fn send_header_after_connected(
-    mut network_events: EventReader<NetworkNodeEvent>,
-    q_node: Query<(Entity, &NetworkNode), With<NetworkPeer>>,
-    mut commands: Commands,
+/// Error of the telemetry pipeline.
+#[derive(Debug, thiserror::Error)]
+pub enum SyncError {
+    #[error("error writing records")]
+    Io(#[from] io::Error),
+    #[error("property `{0}` is not a finite number")]
+    NonFinite(PropertyKey),
+    #[error("network error: {0}")]
+    Network(String),
+}
+
+/// Sent when a peer or an object could not be synced, which is then handled according to the
+/// [`ErrorPolicy`].
+#[derive(Event, Debug)]
+pub struct TacviewError {
+    /// Peer the error occurred for, if any.
+    pub peer: Option<Entity>,
+    /// Object which could not be synced, if any.
+    pub entity: Option<Entity>,
+    pub error: SyncError,
+}
+
+/// How the plugin handles a [`TacviewError`].
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorPolicy {
+    /// Drop the object update or the frame of the peer which failed.
+    #[default]
+    Skip,
+    /// Sync the object again in the next frame, or send a full sync to the peer which failed.
+    Retry,
+    /// Disconnect the peer which failed. Objects which fail are skipped.
+    Disconnect,
+}
+
+impl ErrorPolicy {
+    pub(crate) fn handle_peer_error(self, peer: Entity, commands: &mut Commands) {
+        let Some(mut peer) = commands.get_entity(peer) else {
+            return;
+        };
+        match self {
+            ErrorPolicy::Skip => {}
+            ErrorPolicy::Retry => {
+                peer.insert(NeedFullSync);
+            }
+            ErrorPolicy::Disconnect => peer.despawn(),
+        }
+    }
+}
+
+pub(crate) fn report_error(errors: &mut EventWriter<TacviewError>, error: TacviewError) {
+    error!(
+        "Tacview sync failed for peer {:?}, object {:?}: {}",
+        error.peer, error.entity, error.error
+    );
+    errors.send(error);
+}
+
+/// Send the header to new sinks, they get a full sync in the next frame.
+pub(crate) fn start_sinks(
+    mut q_sinks: Query<(Entity, &mut Sink), Added<Sink>>,
     tacview_res: Res<TacviewResource>,
+    policy: Res<ErrorPolicy>,
+    mut errors: EventWriter<TacviewError>,
+    mut commands: Commands,
 ) {
-    for event in network_events.read() {
-        if event.channel_id != TACVIEW_CHANNEL {
-            continue;
-        }
-        match &event.event {
-            NetworkEvent::Connected => {
-                info!("Tacview Client Connected {:?}", event.node);
-                if let Ok((e, net_node)) = q_node.get(event.node) {
-                    net_node.send(REAL_TIME_PROTOCOL.as_bytes());
-                    commands.entity(e).insert(NeedFullSync);
-                    // meta
-                    let meta = build_meta_data(&tacview_res);
-                    net_node.send(&meta);
-                } else {
-                    warn!("Failed to get network node for {:?}", event.node)
-                }
-            }
-            NetworkEvent::Disconnected => {
-                info!("Tacview Client Disconnected");
+    for (e, mut sink) in q_sinks.iter_mut() {
+        let started = PeerWriter::new().and_then(|peer_writer| {
+            sink.send(&build_meta_data(&tacview_res)?)?;
+            Ok(peer_writer)
+        });
+        match started {
+            Ok(peer_writer) => {
+                commands.entity(e).insert((NeedFullSync, peer_writer));
             }
-            NetworkEvent::Error(err) => {
-                error!("net err: {:?}", err);
+            Err(err) => {
+                report_error(
+                    &mut errors,
+                    TacviewError {
+                        peer: Some(e),
+                        entity: None,
+                        error: err.into(),
+                    },
+                );
+                policy.handle_peer_error(e, &mut commands);
             }
-            _ => {}
         }
     }
 }
 
-/// build tacview meta data
-fn build_meta_data(host_res: &TacviewResource) -> Vec<u8> {
-    let mut writer = Writer::new(vec![]).unwrap();
-    writer
-        .write(Record::GlobalProperty(GlobalProperty::Title(
-            host_res.title.clone(),
-        )))
-        .unwrap();
-    writer
-        .write(Record::GlobalProperty(GlobalProperty::Category(
-            host_res.category.clone(),
-        )))
-        .unwrap();
-    writer
-        .write(Record::GlobalProperty(GlobalProperty::Author(
-            host_res.author.clone(),
-        )))
-        .unwrap();
-    if let Some(time) = host_res.reference_time {
-        writer
-            .write(Record::GlobalProperty(GlobalProperty::ReferenceTime(
-                time.to_rfc3339_opts(SecondsFormat::Secs, true),
-            )))
-            .unwrap();
+/// build tacview header with the global properties
+fn build_meta_data(host_res: &TacviewResource) -> Result<Vec<u8>, io::Error> {
+    let mut writer = Writer::new(vec![])?;
+    for global in global_properties(host_res) {
+        writer.write(Record::GlobalProperty(global))?;
     }
-    if let Some(time) = host_res.recording_time {
-        writer
-            .write(Record::GlobalProperty(GlobalProperty::RecordingTime(
-                time.to_rfc3339_opts(SecondsFormat::Secs, true),
-            )))
-            .unwrap();
-    }
-    writer
-        .write(Record::GlobalProperty(GlobalProperty::Briefing(
-            host_res.briefing.clone(),
-        )))
-        .unwrap();
-    writer
-        .write(Record::GlobalProperty(GlobalProperty::Debriefing(
-            host_res.debriefing.clone(),
-        )))
-        .unwrap();
-    writer
-        .write(Record::GlobalProperty(GlobalProperty::Comments(
-            host_res.comments.clone(),
-        )))
-        .unwrap();
-    writer
-        .write(Record::GlobalProperty(GlobalProperty::DataSource(
-            host_res.data_source.clone(),
-        )))
-        .unwrap();
-    writer
-        .write(Record::GlobalProperty(GlobalProperty::DataRecorder(
-            host_res.data_recorder.clone(),
-        )))
-        .unwrap();
 
     writer.into_inner()
 }
 
+/// Global properties of the resource which are set.
+fn global_properties(host_res: &TacviewResource) -> Vec<GlobalProperty> {
+    let mut globals = Vec::new();
+    if let Some(time) = host_res.reference_time {
+        globals.push(GlobalProperty::ReferenceTime(
+            time.to_rfc3339_opts(SecondsFormat::Secs, true),
+        ));
+    }
+    if let Some(time) = host_res.recording_time {
+        globals.push(GlobalProperty::RecordingTime(
+            time.to_rfc3339_opts(SecondsFormat::Secs, true),
+        ));
+    }
+    let texts = [
+        (
+            &host_res.title,
+            GlobalProperty::Title as fn(String) -> GlobalProperty,
+        ),
+        (&host_res.category, GlobalProperty::Category),
+        (&host_res.author, GlobalProperty::Author),
+        (&host_res.briefing, GlobalProperty::Briefing),
+        (&host_res.debriefing, GlobalProperty::Debriefing),
+        (&host_res.comments, GlobalProperty::Comments),
+        (&host_res.data_source, GlobalProperty::DataSource),
+        (&host_res.data_recorder, GlobalProperty::DataRecorder),
+    ];
+    for (value, global) in texts {
+        if !value.is_empty() {
+            globals.push(global(value.clone()));
+        }
+    }
+    globals
+}
+
+/// A global property of the resource with an empty value.
+fn cleared_global(global: &GlobalProperty) -> Option<GlobalProperty> {
+    use GlobalProperty::*;
+    let clear = match global {
+        ReferenceTime(_) => ReferenceTime,
+        RecordingTime(_) => RecordingTime,
+        Title(_) => Title,
+        Category(_) => Category,
+        Author(_) => Author,
+        Briefing(_) => Briefing,
+        Debriefing(_) => Debriefing,
+        Comments(_) => Comments,
+        DataSource(_) => DataSource,
+        DataRecorder(_) => DataRecorder,
+        _ => return None,
+    };
+    Some(clear(String::new()))
+}
 
 #[derive(Component)]
 pub struct NeedFullSync;
 
+/// Systems of the plugin, components are gathered into properties before objects are synced.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TacviewSet {
+    Gather,
+    Sync,
+}
+
+/// Events written in the next frame sent to every peer.
+#[derive(Resource, Default)]
+pub(crate) struct PendingEvents(pub(crate) Vec<Event>);
+
+/// A Tacview event written in the current frame for every peer, e.g. a `Bookmark` or `Message`.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct TacviewEvent {
+    pub kind: EventKind,
+    /// Objects the event is about, written as the first parameters.
+    pub entities: Vec<Entity>,
+    /// Parameters written after the objects, like the `key:value` parameters of `Timeout`.
+    pub params: Vec<String>,
+    pub text: Option<String>,
+}
+
+impl TacviewEvent {
+    pub fn new(kind: EventKind) -> Self {
+        Self {
+            kind,
+            entities: Vec::new(),
+            params: Vec::new(),
+            text: None,
+        }
+    }
+
+    pub fn message(text: impl Into<String>) -> Self {
+        Self::new(EventKind::Message).text(text)
+    }
+
+    pub fn bookmark(text: impl Into<String>) -> Self {
+        Self::new(EventKind::Bookmark).text(text)
+    }
+
+    pub fn debug(text: impl Into<String>) -> Self {
+        Self::new(EventKind::Debug).text(text)
+    }
+
+    pub fn entity(mut self, entity: Entity) -> Self {
+        self.entities.push(entity);
+        self
+    }
+
+    pub fn param(mut self, param: impl Into<String>) -> Self {
+        self.params.push(param.into());
+        self
+    }
+
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+}
+
+impl From<TacviewEvent> for Event {
+    fn from(event: TacviewEvent) -> Self {
+        let mut params = event
+            .entities
+            .iter()
+            .map(|e| format!("{:x}", e.to_bits()))
+            .collect::<Vec<_>>();
+        params.extend(event.params);
+        Event {
+            kind: event.kind,
+            params,
+            text: event.text,
+        }
+    }
+}
+
+/// ACMI stream of a connected peer, its buffers are reused every tick.
+#[derive(Component)]
+pub(crate) struct PeerWriter(Writer<Vec<u8>>);
+
+impl PeerWriter {
+    fn new() -> Result<Self, io::Error> {
+        Ok(Self(Writer::new_empty(vec![])?))
+    }
+}
+
 #[derive(Component, Debug, Reflect)]
 pub enum ObjectNeedSync {
     Spawn,
@@ -155,97 +302,685 @@ pub enum ObjectNeedSync {
     Destroy,
 }
 
+impl ObjectNeedSync {
+    /// Whether the object is removed from the recording.
+    pub fn is_removal(&self) -> bool {
+        matches!(
+            self,
+            ObjectNeedSync::LeftArea | ObjectNeedSync::Timeout | ObjectNeedSync::Destroy
+        )
+    }
+}
+
+/// Limits of the frames queued for a peer which does not keep up with the stream.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct PeerQueueLimits {
+    /// Frames a sink may have queued, beyond which its queued incremental frames are dropped and it
+    /// gets a full sync.
+    pub max_queued_frames: usize,
+}
+
+impl Default for PeerQueueLimits {
+    fn default() -> Self {
+        Self {
+            max_queued_frames: 120,
+        }
+    }
+}
+
+/// A peer which fell behind the [`PeerQueueLimits`] and whose queued frames could not all be
+/// dropped, it gets a full sync once its queue is drained.
+#[derive(Component, Debug, Default)]
+pub struct Lagging {
+    /// Objects removed in the frames which were dropped.
+    removed: Vec<u64>,
+}
+
+/// Sent when a peer falls behind and its frames are dropped.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerLagging {
+    pub peer: Entity,
+    /// Frames queued for the peer.
+    pub queued: usize,
+}
 
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 pub(crate) fn update_objects(
     time: Res<Time>,
     tacview_res: Res<TacviewResource>,
-    q_objects: Query<(Entity, &ObjectNeedSync, &Coords, &PropertyList)>,
-    q_node: Query<(Entity, &ChannelId, &NetworkNode, Option<&NeedFullSync>), With<NetworkPeer>>,
+    mut q_objects: Query<(Entity, Option<&ObjectNeedSync>, &Coords, &mut PropertyList)>,
+    mut q_sinks: Query<(
+        Entity,
+        &mut PeerWriter,
+        &mut Sink,
+        Option<&NeedFullSync>,
+        Option<&mut Lagging>,
+    )>,
+    mut pending_events: ResMut<PendingEvents>,
+    mut sent_globals: Local<Vec<GlobalProperty>>,
+    mut recent_removed: Local<VecDeque<Vec<u64>>>,
+    policy: Res<ErrorPolicy>,
+    limits: Res<PeerQueueLimits>,
+    mut errors: EventWriter<TacviewError>,
+    mut lagging_events: EventWriter<PeerLagging>,
+    mut stats: Option<ResMut<SyncStats>>,
     mut commands: Commands,
 ) {
-    for (e, channel_id, net_node, opt_full_sync) in q_node.iter() {
-        if *channel_id != TACVIEW_CHANNEL {
+    // peers which need a full sync received the current globals when they connected
+    let mut changed_globals = Vec::new();
+    if tacview_res.is_changed() {
+        let globals = global_properties(&tacview_res);
+        changed_globals.extend(
+            globals
+                .iter()
+                .filter(|g| !sent_globals.contains(g))
+                .cloned(),
+        );
+        // globals which were cleared are sent with an empty value
+        changed_globals.extend(
+            sent_globals
+                .iter()
+                .filter(|g| {
+                    let kind = std::mem::discriminant(*g);
+                    !globals.iter().any(|n| std::mem::discriminant(n) == kind)
+                })
+                .filter_map(cleared_global),
+        );
+        *sent_globals = globals;
+    }
+
+    // objects which cannot be written are left out of the frame of every peer, removals are
+    // written without their properties
+    let mut failed = Vec::new();
+    for (entity, need_sync, coords, props_list) in q_objects.iter() {
+        if need_sync.is_some_and(ObjectNeedSync::is_removal) {
             continue;
         }
-        let mut w = Writer::new_empty(vec![]).unwrap();
+        let result = check_property(&Property::T(coords.clone()))
+            .and_then(|_| props_list.iter().try_for_each(check_property));
+        if let Err(error) = result {
+            report_error(
+                &mut errors,
+                TacviewError {
+                    peer: None,
+                    entity: Some(entity),
+                    error,
+                },
+            );
+            failed.push(entity);
+        }
+    }
 
-        let need_full_sync = opt_full_sync.is_some();
+    let removed = q_objects
+        .iter()
+        .filter(|(_, need_sync, ..)| need_sync.is_some_and(ObjectNeedSync::is_removal))
+        .map(|(entity, ..)| entity.to_bits())
+        .collect::<Vec<_>>();
+    // objects removed in the frames a lagging peer may drop, which are at most the queued ones
+    recent_removed.push_back(removed.clone());
+    while recent_removed.len() > limits.max_queued_frames + 1 {
+        recent_removed.pop_front();
+    }
 
-        if need_full_sync {
-            // meta
-            let meta = build_meta_data(&tacview_res);
-            net_node.send(&meta);
+    let time = frame_time(&time, &tacview_res);
+    let mut synced = false;
+    for (e, mut peer_writer, mut sink, opt_full_sync, opt_lagging) in q_sinks.iter_mut() {
+        let queued = sink.queued();
+        // objects removed in the frames the peer missed are removed before its full sync
+        let mut resync_removed = Vec::new();
+        let mut resync = false;
+        if let Some(mut lagging) = opt_lagging {
+            lagging.removed.extend(&removed);
+            if queued > 0 {
+                synced = true;
+                if let Some(stats) = stats.as_mut() {
+                    stats.frames_dropped += 1;
+                }
+                continue;
+            }
+            resync_removed = std::mem::take(&mut lagging.removed);
+            resync = true;
+            commands.entity(e).remove::<Lagging>();
+        } else if opt_full_sync.is_none() && queued >= limits.max_queued_frames {
+            warn!("Tacview peer {e:?} fell behind with {queued} frames queued");
+            lagging_events.send(PeerLagging { peer: e, queued });
+            let dropped = sink.drop_queued();
+            if let Some(stats) = stats.as_mut() {
+                stats.frames_dropped += dropped;
+            }
+            resync_removed = recent_removed.iter().flatten().copied().collect();
+            resync = true;
+            if sink.queued() > 0 {
+                // the frames which could not be dropped are delivered before the full sync
+                commands.entity(e).insert(Lagging {
+                    removed: resync_removed,
+                });
+                synced = true;
+                if let Some(stats) = stats.as_mut() {
+                    stats.frames_dropped += 1;
+                }
+                continue;
+            }
         }
+        let need_full_sync = opt_full_sync.is_some() || resync;
 
-        let frame_time = if let Some(recording_time) = tacview_res.recording_time {
-            (Utc::now() - recording_time).num_milliseconds() as f64 / 1000.0
+        let w = &mut peer_writer.0;
+        let objects = q_objects
+            .iter()
+            .filter(|(entity, ..)| !failed.contains(entity))
+            .filter_map(|(entity, need_sync, coords, props_list)| {
+                // a full sync spawns every object which is not removed
+                let sync_kind = if need_full_sync {
+                    if need_sync.is_some_and(ObjectNeedSync::is_removal) {
+                        return None;
+                    }
+                    &ObjectNeedSync::Spawn
+                } else {
+                    need_sync?
+                };
+                Some((entity, sync_kind, coords, props_list))
+            });
+        // new peers received the current globals with the header
+        let globals = if resync {
+            &sent_globals[..]
+        } else if need_full_sync {
+            &[][..]
         } else {
-            time.elapsed_seconds_f64()
+            &changed_globals[..]
         };
-        w.write(Record::Frame(frame_time)).unwrap();
+        let start = Instant::now();
+        let written = write_frame(
+            w,
+            time,
+            globals,
+            &pending_events.0,
+            &resync_removed,
+            objects,
+        );
+        if let Some(stats) = stats.as_mut() {
+            stats.serialization += start.elapsed();
+        }
+        let sent = written.and_then(|records| {
+            if !w.get_ref().is_empty() {
+                sink.send(w.get_ref())?;
+            }
+            Ok(records)
+        });
+        match sent {
+            Ok(records) => {
+                if let Some(stats) = stats.as_mut() {
+                    let throughput = stats.peers.entry(e).or_default();
+                    throughput.records += records;
+                    throughput.bytes += w.get_ref().len();
+                    if let Some(size) = sink.recorded_size() {
+                        *stats.recorder_size.get_or_insert(0) += size;
+                    }
+                }
+                synced = true;
+                if need_full_sync {
+                    commands.entity(e).remove::<NeedFullSync>();
+                }
+            }
+            Err(err) => {
+                report_error(
+                    &mut errors,
+                    TacviewError {
+                        peer: Some(e),
+                        entity: None,
+                        error: err.into(),
+                    },
+                );
+                policy.handle_peer_error(e, &mut commands);
+            }
+        }
+        w.get_mut().clear();
+    }
+    pending_events.0.clear();
 
+    if synced {
+        if let Some(stats) = stats.as_mut() {
+            stats.objects = q_objects
+                .iter()
+                .filter(|(entity, need_sync, ..)| need_sync.is_some() && !failed.contains(entity))
+                .count();
+        }
+        for (entity, need_sync, _, mut props_list) in q_objects.iter_mut() {
+            if failed.contains(&entity) && *policy == ErrorPolicy::Retry {
+                continue;
+            }
+            if need_sync.is_some() {
+                commands.entity(entity).remove::<ObjectNeedSync>();
+            }
+            if props_list.has_changes() {
+                props_list.clear_changes();
+            }
+        }
+    }
+}
 
-        for (entity, need_sync, coords, props_list) in q_objects.iter() {
-            let mut props = vec![Property::T(coords.clone())];
+/// Write a frame with the changed globals, pending events, removed objects and objects to sync,
+/// returns the number of records written.
+fn write_frame<'a>(
+    w: &mut Writer<Vec<u8>>,
+    time: f64,
+    globals: &[GlobalProperty],
+    events: &[Event],
+    removed: &[u64],
+    objects: impl Iterator<Item = (Entity, &'a ObjectNeedSync, &'a Coords, &'a PropertyList)>,
+) -> Result<usize, io::Error> {
+    w.begin_frame(time)?;
+    let result = write_frame_records(w, globals, events, removed, objects);
+    // end the frame even if it failed, so the writer can be reused
+    let ended = w.end_frame();
+    let records = result?;
+    ended.map(|_| records)
+}
 
-            let sync_kind = if need_full_sync {
-                &ObjectNeedSync::Spawn
-            } else {
-                need_sync
-            };
+fn write_frame_records<'a>(
+    w: &mut Writer<Vec<u8>>,
+    globals: &[GlobalProperty],
+    events: &[Event],
+    removed: &[u64],
+    objects: impl Iterator<Item = (Entity, &'a ObjectNeedSync, &'a Coords, &'a PropertyList)>,
+) -> Result<usize, io::Error> {
+    let mut records = globals.len() + events.len() + removed.len();
+    for global in globals {
+        w.write(Record::GlobalProperty(global.clone()))?;
+    }
+    for event in events {
+        w.write(event.clone())?;
+    }
+    for &id in removed {
+        w.write(Record::Remove(id))?;
+    }
 
-            match sync_kind {
-                ObjectNeedSync::Spawn => {
-                    props.extend(props_list.0.clone());
-                    w.write(Record::Update(Update {
-                        id: entity.to_bits(),
-                        props,
-                    }))
-                        .unwrap();
-                }
-                ObjectNeedSync::Update => {
-                    w.write(Record::Update(Update {
-                        id: entity.to_bits(),
-                        props,
-                    }))
-                        .unwrap();
-                }
-                ObjectNeedSync::Destroy => {
-                    w.write(Record::Remove(entity.to_bits())).unwrap();
-                    w.write(Event {
-                        kind: EventKind::Destroyed,
-                        params: vec![entity.to_bits().to_string()],
-                        text: None,
-                    })
-                        .unwrap();
-                }
-                ObjectNeedSync::LeftArea => {
-                    w.write(Record::Remove(entity.to_bits())).unwrap();
-                    w.write(Event {
-                        kind: EventKind::LeftArea,
-                        params: vec![entity.to_bits().to_string()],
-                        text: None,
-                    })
-                        .unwrap();
-                }
-                ObjectNeedSync::Timeout => {
-                    w.write(Record::Remove(entity.to_bits())).unwrap();
-                    w.write(Event {
-                        kind: EventKind::Timeout,
-                        params: vec![entity.to_bits().to_string()],
-                        text: None,
-                    })
-                        .unwrap();
-                }
+    for (entity, sync_kind, coords, props_list) in objects {
+        let id = entity.to_bits();
+        let mut props = vec![Property::T(coords.clone())];
+        let removed = match sync_kind {
+            ObjectNeedSync::Spawn => {
+                props.extend(props_list.iter().cloned());
+                w.write(Update { id, props })?;
+                records += 1;
+                continue;
+            }
+            ObjectNeedSync::Update => {
+                props.extend(props_list.changes().cloned());
+                w.write(Update { id, props })?;
+                records += 1;
+                continue;
             }
+            ObjectNeedSync::Destroy => EventKind::Destroyed,
+            ObjectNeedSync::LeftArea => EventKind::LeftArea,
+            ObjectNeedSync::Timeout => EventKind::Timeout,
+        };
+        w.write(Record::Remove(id))?;
+        w.write(Event {
+            kind: removed,
+            params: vec![format!("{id:x}")],
+            text: None,
+        })?;
+        records += 2;
+    }
+    Ok(records)
+}
+
+/// Check a property can be written, numbers must be finite.
+fn check_property(property: &Property) -> Result<(), SyncError> {
+    let finite = match property {
+        Property::T(c) => [
+            c.longitude,
+            c.latitude,
+            c.altitude,
+            c.u,
+            c.v,
+            c.roll,
+            c.pitch,
+            c.yaw,
+            c.heading,
+        ]
+        .into_iter()
+        .flatten()
+        .all(f64::is_finite),
+        p => p.as_f64().is_none_or(f64::is_finite),
+    };
+    if finite {
+        Ok(())
+    } else {
+        Err(SyncError::NonFinite(property.key()))
+    }
+}
 
-            commands.entity(entity).remove::<ObjectNeedSync>();
+/// Time of the current frame, in seconds since the recording time if set.
+fn frame_time(time: &Time, tacview_res: &TacviewResource) -> f64 {
+    if let Some(recording_time) = tacview_res.recording_time {
+        (Utc::now() - recording_time).num_milliseconds() as f64 / 1000.0
+    } else {
+        time.elapsed_seconds_f64()
+    }
+}
+
+/// Generate `TakenOff` and `Landed` events for objects which took off or landed.
+pub(crate) fn detect_takeoff_landing(
+    time: Res<Time>,
+    tacview_res: Res<TacviewResource>,
+    mut detector: ResMut<TakeoffDetector>,
+    q_objects: Query<(Entity, &Coords, &PropertyList), Changed<Coords>>,
+    mut removed: RemovedComponents<PropertyList>,
+    mut pending_events: ResMut<PendingEvents>,
+) {
+    for entity in removed.read() {
+        detector.remove(entity.to_bits());
+    }
+
+    let time = frame_time(&time, &tacview_res);
+    for (entity, coords, props_list) in q_objects.iter() {
+        if let Some(event) = detector.update(entity.to_bits(), time, coords, props_list) {
+            pending_events.0.push(event);
         }
+    }
+}
+
+/// Keep the `Label` of an object set to its BRA from a bullseye or BRAA from a viewer, as
+/// `045/32, 25000` or `045/32, 25000, hot`.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BraLabel {
+    /// Bearing, range and altitude from a bullseye entity.
+    Bullseye(Entity),
+    /// Bearing, range, altitude and aspect from a viewer entity.
+    Viewer(Entity),
+}
 
-        net_node.send(&w.into_inner());
+/// Bearing, range and altitude of the `target` entity from the `bullseye` entity.
+pub fn entity_bra(q_coords: &Query<&Coords>, bullseye: Entity, target: Entity) -> Option<Bra> {
+    q_coords.get(bullseye).ok()?.bra(q_coords.get(target).ok()?)
+}
+
+/// Bearing, range, altitude and aspect of the `target` entity from the `viewer` entity.
+pub fn entity_braa(q_coords: &Query<&Coords>, viewer: Entity, target: Entity) -> Option<Braa> {
+    q_coords.get(viewer).ok()?.braa(q_coords.get(target).ok()?)
+}
+
+/// Update the `Label` of objects with a [`BraLabel`], and sync them when it changed.
+pub(crate) fn update_bra_labels(
+    q_coords: Query<&Coords>,
+    mut q_labels: Query<(
+        Entity,
+        &BraLabel,
+        &mut PropertyList,
+        Option<&ObjectNeedSync>,
+    )>,
+    mut commands: Commands,
+) {
+    for (entity, bra_label, mut props_list, need_sync) in q_labels.iter_mut() {
+        let label = match *bra_label {
+            BraLabel::Bullseye(bullseye) => {
+                entity_bra(&q_coords, bullseye, entity).map(|bra| bra.to_string())
+            }
+            BraLabel::Viewer(viewer) => {
+                entity_braa(&q_coords, viewer, entity).map(|braa| braa.to_string())
+            }
+        };
+        let Some(label) = label else {
+            continue;
+        };
 
-        if need_full_sync {
-            commands.entity(e).remove::<NeedFullSync>();
+        if matches!(props_list.get(&PropertyKey::Label), Some(Property::Label(v)) if *v == label) {
+            continue;
         }
+        props_list.set(Property::Label(label));
+        if need_sync.is_none() {
+            commands.entity(entity).insert(ObjectNeedSync::Update);
+        }
+    }
+}
+
+/// Gather changed components into the properties of their entity, and sync them.
+pub(crate) fn sync_component<T: TacviewComponent>(
+    mut q_objects: Query<(Entity, &T, &mut PropertyList, Option<&ObjectNeedSync>), Changed<T>>,
+    mut commands: Commands,
+) {
+    for (entity, component, mut props_list, need_sync) in q_objects.iter_mut() {
+        let mut changed = false;
+        for property in component.to_properties() {
+            if props_list.get(&property.key()) != Some(&property) {
+                props_list.set(property);
+                changed = true;
+            }
+        }
+        if changed && need_sync.is_none() {
+            commands.entity(entity).insert(ObjectNeedSync::Update);
+        }
+    }
+}
+
+/// Queue events sent from gameplay systems for the current frame.
+pub(crate) fn collect_events(
+    mut events: EventReader<TacviewEvent>,
+    mut pending_events: ResMut<PendingEvents>,
+) {
+    pending_events
+        .0
+        .extend(events.read().cloned().map(Event::from));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{spawn_memory_peer, spawn_object, test_app};
+
+    #[test]
+    fn test_global_properties() {
+        let mut res = TacviewResource {
+            title: "Red Flag".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            global_properties(&res),
+            [GlobalProperty::Title("Red Flag".to_string())]
+        );
+
+        res.comments = "Part of the recording is missing".to_string();
+        let meta = String::from_utf8(build_meta_data(&res).unwrap()).unwrap();
+        assert!(meta.ends_with("0,Title=Red Flag\n0,Comments=Part of the recording is missing\n"));
+        assert!(!meta.contains("Briefing"));
+    }
+
+    #[test]
+    fn test_changed_globals() {
+        let mut app = test_app();
+        app.insert_resource(TacviewResource {
+            title: "Red Flag".to_string(),
+            ..Default::default()
+        });
+        let (_, memory) = spawn_memory_peer(&mut app.world);
+        app.update();
+        memory.take();
+
+        let globals_sent = |app: &mut App, comments: &str| {
+            app.world.resource_mut::<TacviewResource>().comments = comments.to_string();
+            app.update();
+            let stream = String::from_utf8(memory.take()).unwrap();
+            stream
+                .lines()
+                .filter(|line| line.starts_with("0,"))
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            globals_sent(&mut app, "Part of the recording is missing"),
+            ["0,Comments=Part of the recording is missing"]
+        );
+        assert_eq!(globals_sent(&mut app, ""), ["0,Comments="]);
+    }
+
+    #[test]
+    fn test_check_property() {
+        assert!(check_property(&Property::Health(0.5)).is_ok());
+        assert!(matches!(
+            check_property(&Property::Health(f64::NAN)),
+            Err(SyncError::NonFinite(PropertyKey::Health))
+        ));
+        let coords = Coords {
+            altitude: Some(f64::INFINITY),
+            ..Default::default()
+        };
+        assert!(check_property(&Property::T(coords)).is_err());
+    }
+
+    #[test]
+    fn test_write_frame() {
+        let mut w = Writer::new_empty(vec![]).unwrap();
+        let props = PropertyList::from(vec![Property::Health(0.5)]);
+        let coords = Coords::default();
+        let objects = [
+            (Entity::from_raw(1), &ObjectNeedSync::Spawn, &coords, &props),
+            (
+                Entity::from_raw(2),
+                &ObjectNeedSync::Destroy,
+                &coords,
+                &props,
+            ),
+        ];
+        let globals = [GlobalProperty::Title("Red Flag".to_string())];
+        let records = write_frame(&mut w, 1.0, &globals, &[], &[], objects.into_iter()).unwrap();
+        assert_eq!(records, 4);
+    }
+
+    #[test]
+    fn test_tacview_event() {
+        let mut app = test_app();
+        let (_, memory) = spawn_memory_peer(&mut app.world);
+        let object = spawn_object(&mut app.world, vec![]);
+        app.update();
+        memory.take();
+
+        app.world
+            .send_event(TacviewEvent::bookmark("Fox 2").entity(object));
+        app.update();
+        let stream = String::from_utf8(memory.take()).unwrap();
+        let lines = stream.lines().collect::<Vec<_>>();
+        // the event is written in the frame of the update it was sent in
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with('#'));
+        assert_eq!(
+            lines[1],
+            format!("0,Event=Bookmark|{:x}|Fox 2", object.to_bits())
+        );
+        assert!(app.world.resource::<PendingEvents>().0.is_empty());
+    }
+
+    #[test]
+    fn test_detect_takeoff() {
+        use crate::record::Tag;
+
+        #[derive(Resource, Default)]
+        struct Detected(Vec<Event>);
+
+        // the events are cleared once the frame is written
+        fn read_pending_events(pending: Res<PendingEvents>, mut detected: ResMut<Detected>) {
+            detected.0.extend(pending.0.iter().cloned());
+        }
+
+        let mut app = test_app();
+        // detection is opt-in
+        assert!(!app.world.contains_resource::<TakeoffDetector>());
+        app.init_resource::<TakeoffDetector>()
+            .init_resource::<Detected>()
+            .add_systems(
+                Update,
+                read_pending_events
+                    .after(detect_takeoff_landing)
+                    .before(update_objects),
+            );
+        let aircraft = app
+            .world
+            .spawn((
+                Coords::default().position(0.0, 0.0, 100.0),
+                PropertyList::from(vec![
+                    Property::Type([Tag::Air, Tag::FixedWing].into()),
+                    Property::IAS(5.0),
+                ]),
+            ))
+            .id();
+        app.update();
+        assert!(app.world.resource::<Detected>().0.is_empty());
+
+        *app.world.get_mut::<Coords>(aircraft).unwrap() =
+            Coords::default().position(0.0, 0.0, 200.0);
+        app.world
+            .get_mut::<PropertyList>(aircraft)
+            .unwrap()
+            .set(Property::IAS(80.0));
+        app.update();
+        let detected = &app.world.resource::<Detected>().0;
+        assert_eq!(detected.len(), 1);
+        assert_eq!(detected[0].kind, EventKind::TakenOff);
+        assert_eq!(detected[0].params, [format!("{:x}", aircraft.to_bits())]);
+    }
+
+    #[test]
+    fn test_bra_label() {
+        let mut app = test_app();
+        let bullseye = app
+            .world
+            .spawn(Coords::default().position(0.0, 0.0, 0.0))
+            .id();
+        let target = app
+            .world
+            .spawn((
+                Coords::default().position(1.0, 0.0, 7620.0),
+                PropertyList::new(),
+                BraLabel::Bullseye(bullseye),
+            ))
+            .id();
+        let label =
+            |world: &World| match world.get::<PropertyList>(target)?.get(&PropertyKey::Label) {
+                Some(Property::Label(label)) => Some(label.clone()),
+                _ => None,
+            };
+
+        app.update();
+        let first = label(&app.world).unwrap();
+        assert!(
+            first.starts_with("000/") && first.ends_with(", 25000"),
+            "{first}"
+        );
+        assert!(app.world.get::<ObjectNeedSync>(target).is_some());
+
+        // the label is only synced when it changed
+        app.world.entity_mut(target).remove::<ObjectNeedSync>();
+        app.update();
+        assert!(app.world.get::<ObjectNeedSync>(target).is_none());
+
+        *app.world.get_mut::<Coords>(target).unwrap() =
+            Coords::default().position(0.0, 1.0, 7620.0);
+        app.update();
+        let moved = label(&app.world).unwrap();
+        assert!(moved.starts_with("090/"), "{moved}");
+        assert!(app.world.get::<ObjectNeedSync>(target).is_some());
+    }
+
+    #[test]
+    fn test_remove_invalid_object() {
+        let mut app = test_app();
+        let (_, memory) = spawn_memory_peer(&mut app.world);
+        let object = spawn_object(&mut app.world, vec![Property::Health(0.5)]);
+        app.update();
+        memory.take();
+
+        // an object which cannot be written is still removed
+        app.world
+            .get_mut::<PropertyList>(object)
+            .unwrap()
+            .set(Property::Health(f64::NAN));
+        app.world.entity_mut(object).insert(ObjectNeedSync::Destroy);
+        app.update();
+        let stream = String::from_utf8(memory.take()).unwrap();
+        let id = object.to_bits();
+        assert!(stream.lines().any(|line| line == format!("-{id:x}")));
+        assert!(stream.contains(&format!("0,Event=Destroyed|{id:x}|")));
+        assert!(app.world.get::<ObjectNeedSync>(object).is_none());
     }
 }
@@ -0,0 +1,278 @@
+//! Real-time telemetry server on a [`TcpListener`], without `bevy_octopus`.
+//!
+//! Clients are accepted and written to on worker threads, the ECS gets a [`TcpClient`] entity with
+//! a [`Sink`] for each of them which is despawned when the client disconnects. The frames of a
+//! client wait in a queue bounded by the [`PeerQueueLimits`].
+
+use std::io::{self, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::sink::{QueueSink, Sink};
+use crate::systems::{report_error, SyncError, TacviewError, REAL_TIME_PROTOCOL};
+use crate::{PeerQueueLimits, TacviewSet};
+
+/// Default port of Tacview real-time telemetry.
+pub const DEFAULT_PORT: u16 = 42674;
+
+/// Serve real-time telemetry on `addr`, along with the [`TacviewPlugin`](crate::TacviewPlugin).
+pub struct TacviewServerPlugin {
+    pub addr: SocketAddr,
+}
+
+impl Default for TacviewServerPlugin {
+    fn default() -> Self {
+        Self {
+            addr: (Ipv4Addr::UNSPECIFIED, DEFAULT_PORT).into(),
+        }
+    }
+}
+
+impl Plugin for TacviewServerPlugin {
+    fn build(&self, app: &mut App) {
+        match TcpServer::bind(self.addr) {
+            Ok(server) => {
+                info!("Tacview server listening on {}", server.local_addr());
+                app.insert_resource(server);
+            }
+            Err(err) => error!("Failed to start Tacview server on {}: {err}", self.addr),
+        }
+        app.add_systems(
+            Update,
+            accept_tcp_clients
+                .run_if(resource_exists::<TcpServer>)
+                .before(TacviewSet::Sync),
+        );
+    }
+}
+
+/// How long the accepting thread waits between polls of the listener.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A listening server, clients connect on a worker thread.
+///
+/// The listener is closed when the server is dropped, connected clients are disconnected when
+/// their entity is despawned.
+#[derive(Resource)]
+pub struct TcpServer {
+    addr: SocketAddr,
+    events: Receiver<ClientEvent>,
+    /// Sender of the events of the threads writing to clients.
+    sender: Sender<ClientEvent>,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl TcpServer {
+    pub fn bind(addr: impl ToSocketAddrs) -> Result<Self, io::Error> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let stop = shutdown.clone();
+        let sender = tx.clone();
+        thread::Builder::new()
+            .name("tacview server".to_string())
+            .spawn(move || accept_clients(listener, tx, &stop))?;
+        Ok(Self {
+            addr,
+            events: rx,
+            sender,
+            shutdown,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for TcpServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+    }
+}
+
+/// A client connected to the [`TcpServer`].
+#[derive(Component, Debug)]
+pub struct TcpClient {
+    pub addr: SocketAddr,
+}
+
+enum ClientEvent {
+    Connected {
+        id: u64,
+        addr: SocketAddr,
+        stream: TcpStream,
+    },
+    Disconnected {
+        id: u64,
+        error: Option<io::Error>,
+    },
+}
+
+/// Accept clients until the server is dropped, which closes the listener.
+fn accept_clients(listener: TcpListener, events: Sender<ClientEvent>, shutdown: &AtomicBool) {
+    let mut next_id = 0;
+    while !shutdown.load(Ordering::Relaxed) {
+        let (stream, addr) = match listener.accept().and_then(start_client) {
+            Ok(accepted) => accepted,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                thread::sleep(ACCEPT_INTERVAL);
+                continue;
+            }
+            Err(err) => {
+                warn!("Failed to accept Tacview client: {err}");
+                continue;
+            }
+        };
+        let id = next_id;
+        next_id += 1;
+        if events
+            .send(ClientEvent::Connected { id, addr, stream })
+            .is_err()
+        {
+            // the server was dropped
+            return;
+        }
+    }
+}
+
+/// Write the handshake to an accepted client, without waiting for the ECS.
+fn start_client(
+    (mut stream, addr): (TcpStream, SocketAddr),
+) -> Result<(TcpStream, SocketAddr), io::Error> {
+    // the stream may inherit the non-blocking mode of the listener
+    stream.set_nonblocking(false)?;
+    stream.set_nodelay(true)?;
+    stream.write_all(REAL_TIME_PROTOCOL.as_bytes())?;
+    Ok((stream, addr))
+}
+
+/// Write the stream to a client, until its sink is dropped.
+fn write_client(mut stream: TcpStream, frames: Receiver<Vec<u8>>) -> Result<(), io::Error> {
+    for data in frames {
+        stream.write_all(&data)?;
+    }
+    Ok(())
+}
+
+/// Spawn a sink for connected clients, and despawn it when they disconnect.
+fn accept_tcp_clients(
+    server: Res<TcpServer>,
+    limits: Res<PeerQueueLimits>,
+    mut clients: Local<HashMap<u64, Entity>>,
+    mut errors: EventWriter<TacviewError>,
+    mut commands: Commands,
+) {
+    for event in server.events.try_iter() {
+        match event {
+            ClientEvent::Connected { id, addr, stream } => {
+                info!("Tacview Client Connected {addr}");
+                let (sink, frames) = QueueSink::new(limits.max_queued_frames);
+                let events = server.sender.clone();
+                let spawned = thread::Builder::new()
+                    .name(format!("tacview client {addr}"))
+                    .spawn(move || {
+                        let error = write_client(stream, frames).err();
+                        let _ = events.send(ClientEvent::Disconnected { id, error });
+                    });
+                if let Err(err) = spawned {
+                    warn!("Failed to start writing to Tacview client {addr}: {err}");
+                    continue;
+                }
+                let entity = commands.spawn((TcpClient { addr }, Sink::new(sink))).id();
+                clients.insert(id, entity);
+            }
+            ClientEvent::Disconnected { id, error } => {
+                info!("Tacview Client Disconnected");
+                let Some(entity) = clients.remove(&id) else {
+                    continue;
+                };
+                if let Some(err) = error {
+                    report_error(
+                        &mut errors,
+                        TacviewError {
+                            peer: Some(entity),
+                            entity: None,
+                            error: SyncError::Io(err),
+                        },
+                    );
+                }
+                if let Some(mut entity) = commands.get_entity(entity) {
+                    entity.despawn();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+    use crate::test_utils::test_app;
+
+    fn update_until(app: &mut App, f: impl Fn(&mut World) -> bool) {
+        for _ in 0..200 {
+            app.update();
+            if f(&mut app.world) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        panic!("timed out");
+    }
+
+    fn clients(world: &mut World) -> usize {
+        world.query::<&TcpClient>().iter(world).len()
+    }
+
+    #[test]
+    fn test_server() {
+        let mut app = test_app();
+        app.add_plugins(TacviewServerPlugin {
+            addr: (Ipv4Addr::LOCALHOST, 0).into(),
+        });
+        let addr = app.world.resource::<TcpServer>().local_addr();
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        update_until(&mut app, |world| clients(world) == 1);
+        app.update();
+
+        let header = format!("{REAL_TIME_PROTOCOL}FileType=text/acmi/tacview\nFileVersion=2.2\n");
+        let mut received = vec![0; header.len()];
+        client.read_exact(&mut received).unwrap();
+        assert_eq!(String::from_utf8(received).unwrap(), header);
+
+        drop(client);
+        update_until(&mut app, |world| clients(world) == 0);
+    }
+
+    #[test]
+    fn test_drop_server() {
+        let server = TcpServer::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = server.local_addr();
+        drop(server);
+
+        // the port is freed once the accepting thread noticed
+        for _ in 0..200 {
+            if TcpListener::bind(addr).is_ok() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        panic!("port not freed");
+    }
+}
@@ -0,0 +1,41 @@
+//! Setup shared by the tests of the plugin.
+
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use bevy::utils::Duration;
+use bevy::MinimalPlugins;
+
+use crate::record::{Coords, Property, PropertyList};
+use crate::sink::{MemorySink, Sink};
+use crate::systems::ObjectNeedSync;
+use crate::TacviewPlugin;
+
+/// Time between two updates of a [`test_app`].
+pub(crate) const FRAME_TIME: Duration = Duration::from_millis(20);
+
+/// An app with the [`TacviewPlugin`], whose time advances by [`FRAME_TIME`] every update instead
+/// of following the wall clock.
+pub(crate) fn test_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, TacviewPlugin))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME_TIME));
+    app
+}
+
+/// Spawn a peer which keeps the stream in memory.
+pub(crate) fn spawn_memory_peer(world: &mut World) -> (Entity, MemorySink) {
+    let memory = MemorySink::new();
+    let peer = world.spawn(Sink::new(memory.clone())).id();
+    (peer, memory)
+}
+
+/// Spawn an object at the origin with `props`, to be synced in the next update.
+pub(crate) fn spawn_object(world: &mut World, props: Vec<Property>) -> Entity {
+    world
+        .spawn((
+            Coords::default(),
+            PropertyList::from(props),
+            ObjectNeedSync::Spawn,
+        ))
+        .id()
+}
@@ -0,0 +1,395 @@
+//! A mock Tacview client to test the plugin in headless `cargo test`.
+//!
+//! The client reads the stream of a [`Sink`] it spawned, or of a real-time telemetry server, and
+//! replays it into a [`WorldState`], keeping the state at the end of every frame:
+//!
+//! ```rust,ignore
+//! let mut client = MockClient::connect(&mut app.world);
+//! client.update(&mut app)?;
+//! client.assert_object("Viper 1", &[Property::Health(0.5)]);
+//! client.assert_object_at(12.0, "Viper 1", &[Property::Health(1.0)]);
+//! ```
+
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use bevy::prelude::*;
+use crossbeam_channel::Receiver;
+
+use crate::parser::{is_continued, Parser};
+use crate::record::{Event, Property, Record};
+use crate::sink::{ChannelSink, Sink};
+use crate::state::{ObjectState, WorldState};
+use crate::ParseError;
+
+/// Handshake sent by the mock client to a real-time telemetry server.
+const CLIENT_HANDSHAKE: &str = "XtraLib.Stream.0
+Tacview.RealTimeTelemetry.0
+Mock Client
+0\0";
+
+#[derive(Debug, thiserror::Error)]
+pub enum MockClientError {
+    #[error("error reading from the server")]
+    Io(#[from] io::Error),
+    #[error("invalid handshake `{0}`")]
+    Handshake(String),
+    #[error("error parsing the stream")]
+    Parse(#[from] ParseError),
+}
+
+enum Source {
+    Channel(Receiver<Vec<u8>>),
+    Tcp(TcpStream),
+}
+
+/// An in-process Tacview client.
+pub struct MockClient {
+    source: Source,
+    handshake: Option<String>,
+    /// Received data which does not end with a complete line yet.
+    pending: Vec<u8>,
+    header: bool,
+    state: WorldState,
+    /// The state at the end of every frame received before the current one.
+    frames: Vec<WorldState>,
+    events: Vec<Event>,
+    /// The server closed the connection.
+    disconnected: bool,
+}
+
+impl MockClient {
+    fn new(source: Source) -> Self {
+        Self {
+            source,
+            handshake: None,
+            pending: Vec::new(),
+            header: false,
+            state: WorldState::new(),
+            frames: Vec::new(),
+            events: Vec::new(),
+            disconnected: false,
+        }
+    }
+
+    /// A client reading the stream of the returned sink.
+    pub fn sink() -> (Sink, Self) {
+        let (sink, rx) = ChannelSink::unbounded();
+        (Sink::new(sink), Self::new(Source::Channel(rx)))
+    }
+
+    /// Connect to the plugin by spawning a sink, the client gets the header and a full sync in
+    /// the next update.
+    pub fn connect(world: &mut World) -> Self {
+        let (sink, client) = Self::sink();
+        world.spawn(sink);
+        client
+    }
+
+    /// Connect to a real-time telemetry server and perform the handshake.
+    pub fn connect_tcp(addr: impl ToSocketAddrs) -> Result<Self, MockClientError> {
+        let mut stream = TcpStream::connect(addr)?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        stream.write_all(CLIENT_HANDSHAKE.as_bytes())?;
+
+        let mut pending = Vec::new();
+        let mut buf = [0; 1024];
+        let end = loop {
+            if let Some(end) = pending.iter().position(|&b| b == 0) {
+                break end;
+            }
+            match stream.read(&mut buf)? {
+                0 => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                n => pending.extend_from_slice(&buf[..n]),
+            }
+        };
+        let handshake = String::from_utf8_lossy(&pending[..end]).into_owned();
+        if !handshake.starts_with("XtraLib.Stream.0\nTacview.RealTimeTelemetry.0\n") {
+            return Err(MockClientError::Handshake(handshake));
+        }
+        pending.drain(..=end);
+        stream.set_nonblocking(true)?;
+
+        let mut client = Self::new(Source::Tcp(stream));
+        client.handshake = Some(handshake);
+        client.pending = pending;
+        Ok(client)
+    }
+
+    /// Run an update of the app and read what was sent.
+    pub fn update(&mut self, app: &mut App) -> Result<(), MockClientError> {
+        app.update();
+        self.poll()
+    }
+
+    /// Read and replay everything received so far.
+    pub fn poll(&mut self) -> Result<(), MockClientError> {
+        match &mut self.source {
+            Source::Channel(rx) => {
+                for data in rx.try_iter() {
+                    self.pending.extend_from_slice(&data);
+                }
+            }
+            Source::Tcp(stream) => {
+                let mut buf = [0; 4096];
+                loop {
+                    match stream.read(&mut buf) {
+                        Ok(0) => {
+                            self.disconnected = true;
+                            break;
+                        }
+                        Ok(n) => self.pending.extend_from_slice(&buf[..n]),
+                        Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+            }
+        }
+
+        // only complete lines are parsed, the rest waits for more data
+        let complete = (0..self.pending.len())
+            .rev()
+            .find(|&i| self.pending[i] == b'\n' && !is_continued(&self.pending, i))
+            .map_or(0, |i| i + 1);
+        let pending = std::mem::take(&mut self.pending);
+        let text = match std::str::from_utf8(&pending[..complete]) {
+            Ok(text) => text,
+            Err(err) => {
+                self.pending = pending;
+                return Err(ParseError::from(err).into());
+            }
+        };
+        let result = self.replay(text);
+        // lines which failed to parse are dropped
+        let consumed = if matches!(result, Ok(false)) {
+            0
+        } else {
+            complete
+        };
+        self.pending = pending;
+        self.pending.drain(..consumed);
+        result.map(|_| ())
+    }
+
+    /// Replay complete lines, returns whether they were consumed.
+    fn replay(&mut self, text: &str) -> Result<bool, MockClientError> {
+        let parser = if self.header {
+            Parser::without_header(text)
+        } else if text.lines().count() < 2 {
+            return Ok(false);
+        } else {
+            self.header = true;
+            Parser::new(text)?
+        };
+        for record in parser {
+            let record = record?.to_record()?;
+            match &record {
+                Record::Frame(_) => self.frames.push(self.state.clone()),
+                Record::Event(event) => self.events.push(event.clone()),
+                _ => {}
+            }
+            self.state.apply(&record);
+        }
+        Ok(true)
+    }
+
+    /// The handshake of the server, if connected to one.
+    pub fn handshake(&self) -> Option<&str> {
+        self.handshake.as_deref()
+    }
+
+    /// Whether the server closed the connection, everything it sent before has been replayed.
+    pub fn disconnected(&self) -> bool {
+        self.disconnected
+    }
+
+    /// The state at the latest frame received.
+    pub fn state(&self) -> &WorldState {
+        &self.state
+    }
+
+    /// The state at the end of the latest frame at or before `time`.
+    pub fn state_at(&self, time: f64) -> Option<&WorldState> {
+        std::iter::once(&self.state)
+            .chain(self.frames.iter().rev())
+            .find(|state| state.time <= time)
+    }
+
+    /// Events received so far.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// The object with the `call_sign` at the latest frame.
+    pub fn object(&self, call_sign: &str) -> Option<&ObjectState> {
+        find_object(&self.state, call_sign)
+    }
+
+    /// The object with the `call_sign` at the end of the latest frame at or before `time`.
+    pub fn object_at(&self, time: f64, call_sign: &str) -> Option<&ObjectState> {
+        find_object(self.state_at(time)?, call_sign)
+    }
+
+    /// Assert the object with the `call_sign` exists at the latest frame with the `props`.
+    #[track_caller]
+    pub fn assert_object(&self, call_sign: &str, props: &[Property]) {
+        assert_props(self.object(call_sign), self.state.time, call_sign, props);
+    }
+
+    /// Assert the object with the `call_sign` exists at `time` with the `props`.
+    #[track_caller]
+    pub fn assert_object_at(&self, time: f64, call_sign: &str, props: &[Property]) {
+        assert_props(self.object_at(time, call_sign), time, call_sign, props);
+    }
+
+    /// Assert there is no object with the `call_sign` at the latest frame.
+    #[track_caller]
+    pub fn assert_no_object(&self, call_sign: &str) {
+        if let Some(object) = self.object(call_sign) {
+            panic!(
+                "expected no object `{call_sign}` at {}, found {object:?}",
+                self.state.time
+            );
+        }
+    }
+}
+
+fn find_object<'a>(state: &'a WorldState, call_sign: &str) -> Option<&'a ObjectState> {
+    state
+        .objects
+        .values()
+        .find(|object| object.call_sign() == Some(call_sign))
+}
+
+#[track_caller]
+fn assert_props(object: Option<&ObjectState>, time: f64, call_sign: &str, props: &[Property]) {
+    let Some(object) = object else {
+        panic!("expected an object `{call_sign}` at {time}");
+    };
+    for expected in props {
+        let actual = match expected {
+            Property::T(_) => Some(Property::T(object.coords.clone())),
+            p => object.props.get(&p.key()).cloned(),
+        };
+        assert_eq!(
+            actual.as_ref(),
+            Some(expected),
+            "property of object `{call_sign}` at {time}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::PropertyList;
+    use crate::systems::ObjectNeedSync;
+    use crate::test_utils::{spawn_object, test_app};
+
+    #[test]
+    fn test_mock_client() {
+        let mut app = test_app();
+        let mut client = MockClient::connect(&mut app.world);
+        let viper = spawn_object(
+            &mut app.world,
+            vec![
+                Property::CallSign("Viper 1".to_string()),
+                Property::Health(1.0),
+            ],
+        );
+        client.update(&mut app).unwrap();
+        client.assert_object("Viper 1", &[Property::Health(1.0)]);
+        let spawned = client.state().time;
+
+        app.world
+            .get_mut::<PropertyList>(viper)
+            .unwrap()
+            .set(Property::Health(0.5));
+        app.world.entity_mut(viper).insert(ObjectNeedSync::Update);
+        client.update(&mut app).unwrap();
+        client.assert_object("Viper 1", &[Property::Health(0.5)]);
+        client.assert_object_at(spawned, "Viper 1", &[Property::Health(1.0)]);
+
+        app.world.entity_mut(viper).insert(ObjectNeedSync::Destroy);
+        client.update(&mut app).unwrap();
+        client.assert_no_object("Viper 1");
+        assert_eq!(client.events().len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let (mut sink, mut client) = MockClient::sink();
+        let data = b"FileType=text/acmi/tacview\nFileVersion=2.2\n#1\n\xff\n";
+        sink.send(data).unwrap();
+        assert!(matches!(
+            client.poll(),
+            Err(MockClientError::Parse(ParseError::InvalidUtf8(_)))
+        ));
+        // nothing received is lost
+        assert_eq!(client.pending, data);
+    }
+
+    #[test]
+    fn test_disconnected() {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut handshake = Vec::new();
+            let mut byte = [0];
+            while handshake.last() != Some(&0) {
+                stream.read_exact(&mut byte).unwrap();
+                handshake.push(byte[0]);
+            }
+            stream
+                .write_all(
+                    b"XtraLib.Stream.0\nTacview.RealTimeTelemetry.0\nHost\n\0\
+                    FileType=text/acmi/tacview\nFileVersion=2.2\n#1\n",
+                )
+                .unwrap();
+        });
+
+        let mut client = MockClient::connect_tcp(addr).unwrap();
+        server.join().unwrap();
+        for _ in 0..200 {
+            client.poll().unwrap();
+            if client.disconnected() {
+                assert_eq!(client.state().time, 1.0);
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        panic!("disconnection not detected");
+    }
+
+    #[cfg(feature = "tcp")]
+    #[test]
+    fn test_mock_client_tcp() {
+        use crate::tcp::TcpServer;
+        use crate::TacviewServerPlugin;
+
+        let mut app = test_app();
+        app.add_plugins(TacviewServerPlugin {
+            addr: ([127, 0, 0, 1], 0).into(),
+        });
+        spawn_object(
+            &mut app.world,
+            vec![Property::CallSign("Viper 1".to_string())],
+        );
+        let addr = app.world.resource::<TcpServer>().local_addr();
+        let mut client = MockClient::connect_tcp(addr).unwrap();
+        assert!(client.handshake().unwrap().contains("\nHost "));
+
+        for _ in 0..200 {
+            client.update(&mut app).unwrap();
+            if client.object("Viper 1").is_some() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        panic!("object not received");
+    }
+}
@@ -0,0 +1,471 @@
+//! Validation of ACMI recordings.
+//!
+//! Reports issues which make Tacview display a recording with glitches, like frames going back in
+//! time or objects referencing ids which were never declared.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use crate::{
+    record::{Coords, EventKind, GlobalProperty, Property, Record},
+    ParseError, Parser,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IssueKind {
+    /// The line could not be parsed.
+    Parse(String),
+
+    /// A frame time is lower than the one of the previous frame.
+    NonMonotonicFrame { previous: f64, time: f64 },
+
+    /// An object was updated after it has been removed, without being declared again.
+    UpdateAfterRemove { id: u64 },
+
+    /// A removed object id was declared again (with a `Type`) for a new object.
+    IdReused { id: u64 },
+
+    /// `Parent`, `Next`, `FocusedTarget` or `LockedTarget` references an object which is never
+    /// declared in the recording.
+    UnknownReference {
+        id: u64,
+        property: &'static str,
+        target: u64,
+    },
+
+    /// An object never got a `Type`.
+    MissingType { id: u64 },
+
+    /// Absolute latitude outside of [-90, 90].
+    LatitudeOutOfRange { id: u64, latitude: f64 },
+
+    /// Absolute longitude outside of [-180, 180].
+    LongitudeOutOfRange { id: u64, longitude: f64 },
+
+    /// A numeric value of an object is NaN.
+    NaN { id: u64, property: String },
+
+    /// An event references an object which is never declared in the recording.
+    EventUnknownObject { kind: EventKind, id: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    /// The line on which the offending record starts.
+    pub line: usize,
+    pub kind: IssueKind,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    /// Issues ordered by line.
+    pub issues: Vec<Issue>,
+}
+
+/// Checks a stream of records.
+///
+/// Feed every record with its line number to [`Validator::check`], then call
+/// [`Validator::finish`] to get the report. Use [`validate`] to check ACMI text.
+#[derive(Debug, Default)]
+pub struct Validator {
+    issues: Vec<Issue>,
+    frame: Option<f64>,
+    reference_latitude: f64,
+    reference_longitude: f64,
+    /// Objects which have been declared, with the line they have been declared on.
+    objects: HashMap<u64, usize>,
+    typed: HashSet<u64>,
+    removed: HashSet<u64>,
+    references: Vec<(usize, IssueKind)>,
+}
+
+/// Validate ACMI text. Fails only if the header is invalid, unparsable lines are reported as
+/// [`IssueKind::Parse`].
+pub fn validate(input: &str) -> Result<Report, ParseError> {
+    let mut parser = Parser::new(input)?;
+    let mut validator = Validator::new();
+    while let Some(record) = parser.next() {
+        match record.and_then(|r| r.to_record()) {
+            Ok(record) => validator.check(parser.line(), &record),
+            Err(err) => validator.report(parser.line(), IssueKind::Parse(err.to_string())),
+        }
+    }
+    Ok(validator.finish())
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn check(&mut self, line: usize, record: &Record) {
+        match record {
+            Record::GlobalProperty(GlobalProperty::ReferenceLatitude(v)) => {
+                self.reference_latitude = *v;
+            }
+            Record::GlobalProperty(GlobalProperty::ReferenceLongitude(v)) => {
+                self.reference_longitude = *v;
+            }
+            Record::GlobalProperty(_) => {}
+            Record::Frame(time) => {
+                if let Some(previous) = self.frame.filter(|previous| time < previous) {
+                    self.report(
+                        line,
+                        IssueKind::NonMonotonicFrame {
+                            previous,
+                            time: *time,
+                        },
+                    );
+                }
+                self.frame = Some(*time);
+            }
+            Record::Remove(id) => {
+                self.removed.insert(*id);
+            }
+            Record::Event(event) => {
+                for id in event.object_ids() {
+                    self.references.push((
+                        line,
+                        IssueKind::EventUnknownObject {
+                            kind: event.kind.clone(),
+                            id,
+                        },
+                    ));
+                }
+            }
+            Record::Update(update) => {
+                let id = update.id;
+                let declares_type = update.props.iter().any(|p| matches!(p, Property::Type(_)));
+                if self.removed.remove(&id) {
+                    if declares_type {
+                        self.report(line, IssueKind::IdReused { id });
+                        self.typed.remove(&id);
+                        self.objects.insert(id, line);
+                    } else {
+                        self.removed.insert(id);
+                        self.report(line, IssueKind::UpdateAfterRemove { id });
+                    }
+                }
+                self.objects.entry(id).or_insert(line);
+                if declares_type {
+                    self.typed.insert(id);
+                }
+
+                for prop in &update.props {
+                    self.check_property(line, id, prop);
+                }
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> Report {
+        for (line, kind) in std::mem::take(&mut self.references) {
+            let target = match &kind {
+                IssueKind::UnknownReference { target, .. } => *target,
+                IssueKind::EventUnknownObject { id, .. } => *id,
+                _ => continue,
+            };
+            if !self.objects.contains_key(&target) {
+                self.report(line, kind);
+            }
+        }
+
+        let mut untyped = self
+            .objects
+            .iter()
+            .filter(|(id, _)| !self.typed.contains(id))
+            .map(|(id, line)| (*line, *id))
+            .collect::<Vec<_>>();
+        untyped.sort_unstable();
+        for (line, id) in untyped {
+            self.report(line, IssueKind::MissingType { id });
+        }
+
+        self.issues.sort_by_key(|issue| issue.line);
+        Report {
+            issues: self.issues,
+        }
+    }
+
+    fn report(&mut self, line: usize, kind: IssueKind) {
+        self.issues.push(Issue { line, kind });
+    }
+
+    fn check_property(&mut self, line: usize, id: u64, prop: &Property) {
+        let reference = match prop {
+            Property::Parent(target) => Some(("Parent", *target)),
+            Property::Next(target) => Some(("Next", *target)),
+            Property::FocusedTarget(target) => Some(("FocusedTarget", *target)),
+            Property::LockedTarget(target) => Some(("LockedTarget", *target)),
+            _ => None,
+        };
+        if let Some((property, target)) = reference {
+            self.references.push((
+                line,
+                IssueKind::UnknownReference {
+                    id,
+                    property,
+                    target,
+                },
+            ));
+        }
+
+        if let Property::T(coords) = prop {
+            self.check_coords(line, id, coords);
+        } else if prop.as_f64().is_some_and(f64::is_nan) {
+            let property = prop.to_string();
+            let property = property
+                .split_once('=')
+                .map_or(&*property, |(name, _)| name);
+            self.report(
+                line,
+                IssueKind::NaN {
+                    id,
+                    property: property.to_string(),
+                },
+            );
+        }
+    }
+
+    fn check_coords(&mut self, line: usize, id: u64, coords: &Coords) {
+        let values = [
+            coords.longitude,
+            coords.latitude,
+            coords.altitude,
+            coords.u,
+            coords.v,
+            coords.roll,
+            coords.pitch,
+            coords.yaw,
+            coords.heading,
+        ];
+        if values.iter().flatten().any(|v| v.is_nan()) {
+            self.report(
+                line,
+                IssueKind::NaN {
+                    id,
+                    property: "T".to_string(),
+                },
+            );
+            return;
+        }
+
+        if let Some(latitude) = coords.latitude.map(|v| v + self.reference_latitude) {
+            if !(-90.0..=90.0).contains(&latitude) {
+                self.report(line, IssueKind::LatitudeOutOfRange { id, latitude });
+            }
+        }
+        if let Some(longitude) = coords.longitude.map(|v| v + self.reference_longitude) {
+            if !(-180.0..=180.0).contains(&longitude) {
+                self.report(line, IssueKind::LongitudeOutOfRange { id, longitude });
+            }
+        }
+    }
+}
+
+impl IssueKind {
+    pub fn severity(&self) -> Severity {
+        use IssueKind::*;
+        match self {
+            Parse(_)
+            | NonMonotonicFrame { .. }
+            | UpdateAfterRemove { .. }
+            | LatitudeOutOfRange { .. }
+            | LongitudeOutOfRange { .. }
+            | NaN { .. } => Severity::Error,
+            IdReused { .. }
+            | UnknownReference { .. }
+            | MissingType { .. }
+            | EventUnknownObject { .. } => Severity::Warning,
+        }
+    }
+}
+
+impl Report {
+    /// Whether the report contains no errors (warnings are allowed).
+    pub fn is_valid(&self) -> bool {
+        !self
+            .issues
+            .iter()
+            .any(|issue| issue.kind.severity() == Severity::Error)
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Issue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.kind.severity() == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Issue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.kind.severity() == Severity::Warning)
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+
+impl Display for IssueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use IssueKind::*;
+        match self {
+            Parse(err) => write!(f, "failed to parse record: {err}"),
+            NonMonotonicFrame { previous, time } => {
+                write!(f, "frame #{time} is before the previous frame #{previous}")
+            }
+            UpdateAfterRemove { id } => write!(f, "object {id:x} is updated after its removal"),
+            IdReused { id } => write!(f, "id {id:x} is reused for a new object"),
+            UnknownReference {
+                id,
+                property,
+                target,
+            } => write!(
+                f,
+                "object {id:x} {property} references unknown object {target:x}"
+            ),
+            MissingType { id } => write!(f, "object {id:x} has no Type"),
+            LatitudeOutOfRange { id, latitude } => {
+                write!(f, "object {id:x} latitude {latitude} is out of range")
+            }
+            LongitudeOutOfRange { id, longitude } => {
+                write!(f, "object {id:x} longitude {longitude} is out of range")
+            }
+            NaN { id, property } => write!(f, "object {id:x} {property} is NaN"),
+            EventUnknownObject { kind, id } => {
+                write!(f, "{kind:?} event references unknown object {id:x}")
+            }
+        }
+    }
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}: {}: {}",
+            self.line,
+            self.kind.severity(),
+            self.kind
+        )
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{issue}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let report = validate(
+            "FileType=text/acmi/tacview
+FileVersion=2.2
+0,ReferenceLongitude=170
+#0
+a0,T=5|1|1000,Type=Air+FixedWing,Parent=ff
+a1,T=15|1|1000,Type=Air
+#2
+a2,T=0|95|1000,Health=nan
+#1
+-a0
+a0,T=5|2|1000
+0,Event=Destroyed|b0|
+a1,T=
+0,Event=Message|a1|beef|
+0,Event=Timeout|SourceId:a1|AmmoType:FOX2|TargetId:c0|
+",
+        )
+        .unwrap();
+
+        let issues = report
+            .issues
+            .iter()
+            .map(|issue| (issue.line, issue.kind.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            [
+                (
+                    5,
+                    IssueKind::UnknownReference {
+                        id: 0xa0,
+                        property: "Parent",
+                        target: 0xff
+                    }
+                ),
+                (
+                    6,
+                    IssueKind::LongitudeOutOfRange {
+                        id: 0xa1,
+                        longitude: 185.0
+                    }
+                ),
+                (
+                    8,
+                    IssueKind::LatitudeOutOfRange {
+                        id: 0xa2,
+                        latitude: 95.0
+                    }
+                ),
+                (
+                    8,
+                    IssueKind::NaN {
+                        id: 0xa2,
+                        property: "Health".to_string()
+                    }
+                ),
+                (8, IssueKind::MissingType { id: 0xa2 }),
+                (
+                    9,
+                    IssueKind::NonMonotonicFrame {
+                        previous: 2.0,
+                        time: 1.0
+                    }
+                ),
+                (11, IssueKind::UpdateAfterRemove { id: 0xa0 }),
+                (
+                    12,
+                    IssueKind::EventUnknownObject {
+                        kind: EventKind::Destroyed,
+                        id: 0xb0
+                    }
+                ),
+                (
+                    13,
+                    IssueKind::Parse(ParseError::InvalidCoordinateFormat.to_string())
+                ),
+                (
+                    15,
+                    IssueKind::EventUnknownObject {
+                        kind: EventKind::Timeout,
+                        id: 0xc0
+                    }
+                ),
+            ]
+        );
+        assert!(!report.is_valid());
+    }
+}
@@ -1,9 +1,18 @@
 use std::io::{self, Write};
 
-use crate::record::Record;
+use crate::record::{Precision, Record};
 
+/// Writes ACMI records.
+///
+/// Records are formatted into an internal buffer which is reused for the lifetime of the writer.
+/// Outside of a frame every record is passed on to the underlying writer immediately, between
+/// [`Writer::begin_frame`] and [`Writer::end_frame`] the whole frame is passed on in a single
+/// write. Consecutive identical `#frame` markers are only written once.
 pub struct Writer<W> {
     wr: W,
+    buf: Vec<u8>,
+    frame: Option<f64>,
+    in_frame: bool,
 }
 
 impl<W> Writer<W>
@@ -13,19 +22,103 @@ where
     pub fn new(mut wr: W) -> Result<Self, io::Error> {
         writeln!(wr, "FileType=text/acmi/tacview")?;
         writeln!(wr, "FileVersion=2.2")?;
-        Ok(Self { wr })
+        Self::new_empty(wr)
     }
 
     pub fn new_empty(wr: W) -> Result<Self, io::Error> {
-        Ok(Self { wr })
+        Ok(Self {
+            wr,
+            buf: Vec::new(),
+            frame: None,
+            in_frame: false,
+        })
     }
 
     pub fn write(&mut self, record: impl Into<Record>) -> Result<(), io::Error> {
-        writeln!(self.wr, "{}", record.into())?;
+        self.write_record(&record.into())
+    }
+
+    pub fn write_record(&mut self, record: &Record) -> Result<(), io::Error> {
+        if let Record::Frame(time) = record {
+            let time = time.max_precision(2);
+            if self.frame == Some(time) {
+                return Ok(());
+            }
+            self.frame = Some(time);
+        }
+
+        writeln!(self.buf, "{record}")?;
+        if !self.in_frame {
+            self.flush_buf()?;
+        }
+        Ok(())
+    }
+
+    /// Start batching a frame at `time` (in seconds), records written until
+    /// [`Writer::end_frame`] are passed on to the underlying writer in one write.
+    pub fn begin_frame(&mut self, time: f64) -> Result<(), io::Error> {
+        self.in_frame = true;
+        self.write_record(&Record::Frame(time))
+    }
+
+    pub fn end_frame(&mut self) -> Result<(), io::Error> {
+        self.in_frame = false;
+        self.flush_buf()
+    }
+
+    pub fn flush(&mut self) -> Result<(), io::Error> {
+        self.flush_buf()?;
+        self.wr.flush()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.wr
+    }
+
+    /// Mutable access to the underlying writer, e.g. to clear an in-memory buffer after sending it.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.wr
+    }
+
+    /// Write any batched records and return the underlying writer.
+    pub fn into_inner(mut self) -> Result<W, io::Error> {
+        self.flush_buf()?;
+        Ok(self.wr)
+    }
+
+    fn flush_buf(&mut self) -> Result<(), io::Error> {
+        if !self.buf.is_empty() {
+            self.wr.write_all(&self.buf)?;
+            self.buf.clear();
+        }
         Ok(())
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::{Coords, Property, Update};
+
+    #[test]
+    fn test_frame_batching() {
+        let mut w = Writer::new_empty(vec![]).unwrap();
+        w.begin_frame(1.0).unwrap();
+        w.write(Update {
+            id: 0xa0,
+            props: vec![Property::T(Coords::default().position(1.0, 2.0, 3.0))],
+        })
+        .unwrap();
+        assert!(w.get_ref().is_empty());
+        w.end_frame().unwrap();
+
+        w.begin_frame(1.0).unwrap();
+        w.write(Record::Remove(0xa0)).unwrap();
+        w.end_frame().unwrap();
 
-    pub fn into_inner(self) -> W {
-        self.wr
+        assert_eq!(
+            String::from_utf8(w.into_inner().unwrap()).unwrap(),
+            "#1\na0,T=2|1|3\n-a0\n"
+        );
     }
 }